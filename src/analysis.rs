@@ -0,0 +1,180 @@
+use std::path::Path;
+
+use crate::{
+  error::{ErrorGroup, Located},
+  lifetimes::Side,
+  load::load,
+  program::{CheckOptions, Program, Ref},
+  resolve::Resolver,
+  span::{FileId, Files, Span},
+};
+
+/// The result of loading and checking a program, rooted at one file.
+pub struct Analysis {
+  files: Files,
+  program: Option<Program>,
+  diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+  pub file: String,
+  pub start: usize,
+  pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+  Error,
+  Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+  pub severity: Severity,
+  /// Where the diagnostic applies, if anywhere in particular.
+  pub location: Option<Location>,
+  pub message: String,
+  /// Other locations relevant to the diagnostic, each with a description.
+  pub related: Vec<(Location, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+  Type,
+  Agent,
+  Rule,
+  Net,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+  pub name: String,
+  pub kind: SymbolKind,
+  pub location: Location,
+}
+
+const ROOT: FileId = FileId(0);
+
+impl Analysis {
+  /// Loads and checks the program at `path`, reading its files with
+  /// `resolver`.
+  pub fn new(path: impl AsRef<Path>, options: &CheckOptions, resolver: &dyn Resolver) -> Self {
+    let mut files = Files::default();
+    let mut warnings = ErrorGroup::default();
+    let mut diagnostics = vec![];
+    let program = match load(path, &mut files, resolver) {
+      Ok((mut program, mut errors)) => {
+        errors.push(program.check(options, &mut warnings));
+        if let Err(errors) = errors.report("") {
+          diagnostics.extend(errors.locate().into_iter().map(|x| Self::diagnostic(&files, Severity::Error, x)));
+        }
+        Some(program)
+      }
+      Err(errors) => {
+        diagnostics.extend(errors.locate().into_iter().map(|x| Self::diagnostic(&files, Severity::Error, x)));
+        None
+      }
+    };
+    if let Err(warnings) = warnings.report("") {
+      diagnostics.extend(warnings.locate().into_iter().map(|x| Self::diagnostic(&files, Severity::Warning, x)));
+    }
+    Analysis { files, program, diagnostics }
+  }
+
+  fn diagnostic(files: &Files, severity: Severity, located: Located) -> Diagnostic {
+    Diagnostic {
+      severity,
+      location: located.span.map(|span| Self::location(files, span)),
+      message: strip_ansi(&located.message),
+      related: located.related.into_iter().map(|(span, note)| (Self::location(files, span), note)).collect(),
+    }
+  }
+
+  fn location(files: &Files, span: Span) -> Location {
+    Location { file: files[span.file].name.clone(), start: span.start, end: span.end }
+  }
+
+  pub fn diagnostics(&self) -> &[Diagnostic] {
+    &self.diagnostics
+  }
+
+  /// The source of the loaded file named `file`.
+  pub fn source(&self, file: &str) -> Option<&str> {
+    self.files.iter().find(|(_, x)| x.name == file).map(|(_, x)| &*x.contents)
+  }
+
+  fn ref_at(&self, offset: usize) -> Option<(Span, Ref)> {
+    let program = self.program.as_ref()?;
+    let refs = program.refs.iter().filter(|(span, _)| span.file == ROOT && span.start <= offset && offset <= span.end);
+    // prefer a name containing `offset` to one ending just before it
+    refs.min_by_key(|(span, _)| span.end == offset).copied()
+  }
+
+  /// Where the name at `offset` in the root file is defined.
+  pub fn definition(&self, offset: usize) -> Option<Location> {
+    let globals = &self.program.as_ref()?.globals;
+    let span = match self.ref_at(offset)?.1 {
+      Ref::Type(ty) => globals.types.span(ty)?,
+      Ref::Component(component) => globals.components.span(component)?,
+      Ref::Lifetime(decl) => decl?.0,
+    };
+    Some(Self::location(&self.files, span))
+  }
+
+  /// Describes the name at `offset` in the root file: the definition of a
+  /// type, the header of an agent or net, or the side of a lifetime.
+  pub fn hover(&self, offset: usize) -> Option<String> {
+    let program = self.program.as_ref()?;
+    let globals = &program.globals;
+    let (span, target) = self.ref_at(offset)?;
+    Some(match target {
+      Ref::Type(ty) => {
+        let ty = if globals.types.name(ty).starts_with('!') { !ty } else { ty };
+        format!("type {}: {:?}", globals.types.name(ty), globals.types.get(ty).ok()?.polarity)
+      }
+      Ref::Component(component) => {
+        globals.components.get(component).ok()?;
+        match program.nets.iter().find(|net| net.id == component) {
+          Some(net) => globals.show_net_header(component, |i| net.var_ctx.vars.name(net.free_ports[i].0)).to_string(),
+          None => globals.show_agent_header(component).to_string(),
+        }
+      }
+      Ref::Lifetime(decl) => {
+        let name = &self.files[ROOT].contents[span.start..span.end];
+        match decl?.1 {
+          Side::External => format!("{name}: external lifetime"),
+          Side::Internal => format!("{name}: internal lifetime"),
+        }
+      }
+    })
+  }
+
+  /// The items defined in the root file.
+  pub fn symbols(&self) -> Vec<Symbol> {
+    let Some(program) = &self.program else { return vec![] };
+    let (types, components) = (&program.globals.types, &program.globals.components);
+    let symbols = (program.types.iter().map(|x| (types.name(x.id).to_owned(), SymbolKind::Type, x.span)))
+      .chain(program.agents.iter().map(|x| (components.name(x.id).to_owned(), SymbolKind::Agent, x.span)))
+      .chain(program.rules.iter().map(|x| {
+        let name = format!("{}-{}", components.name(x.a.component), components.name(x.b.component));
+        (name, SymbolKind::Rule, x.span)
+      }))
+      .chain(program.nets.iter().map(|x| (components.name(x.id).to_owned(), SymbolKind::Net, x.span)));
+    let symbols = symbols.filter(|(_, _, span)| span.file == ROOT);
+    symbols.map(|(name, kind, span)| Symbol { name, kind, location: Self::location(&self.files, span) }).collect()
+  }
+}
+
+fn strip_ansi(str: &str) -> String {
+  let mut output = String::new();
+  let mut chars = str.chars();
+  while let Some(c) = chars.next() {
+    if c == '\x1b' {
+      chars.by_ref().find(|&c| c == 'm');
+    } else {
+      output.push(c);
+    }
+  }
+  output
+}
@@ -0,0 +1,250 @@
+use std::{
+  collections::HashMap,
+  error::Error,
+  path::{Path, PathBuf},
+};
+
+use inet_lifetimes::{
+  analysis::{Analysis, Location, Severity, SymbolKind},
+  program::CheckOptions,
+  resolve::{FileSystem, Overlay},
+};
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+  notification::{DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, PublishDiagnostics},
+  request::{DocumentSymbolRequest, GotoDefinition, HoverRequest},
+  DiagnosticRelatedInformation, DiagnosticSeverity, DocumentSymbol, DocumentSymbolResponse, GotoDefinitionResponse,
+  Hover, HoverContents, HoverProviderCapability, MarkupContent, MarkupKind, OneOf, Position, PublishDiagnosticsParams,
+  Range, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde_json::Value;
+
+fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
+  let (connection, io_threads) = Connection::stdio();
+  let capabilities = ServerCapabilities {
+    text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+    definition_provider: Some(OneOf::Left(true)),
+    hover_provider: Some(HoverProviderCapability::Simple(true)),
+    document_symbol_provider: Some(OneOf::Left(true)),
+    ..Default::default()
+  };
+  connection.initialize(serde_json::to_value(capabilities)?)?;
+  Server { connection: &connection, docs: HashMap::new(), analyses: HashMap::new() }.run()?;
+  // the writer thread only finishes once the connection is dropped
+  drop(connection);
+  io_threads.join()?;
+  Ok(())
+}
+
+struct Server<'a> {
+  connection: &'a Connection,
+  docs: HashMap<PathBuf, String>,
+  analyses: HashMap<Url, Analysis>,
+}
+
+impl Server<'_> {
+  fn run(&mut self) -> Result<(), Box<dyn Error + Sync + Send>> {
+    for message in &self.connection.receiver {
+      match message {
+        Message::Request(request) => {
+          if self.connection.handle_shutdown(&request)? {
+            return Ok(());
+          }
+          self.handle_request(request)?;
+        }
+        Message::Notification(notification) => self.handle_notification(notification)?,
+        Message::Response(_) => {}
+      }
+    }
+    Ok(())
+  }
+
+  fn handle_notification(&mut self, notification: Notification) -> Result<(), Box<dyn Error + Sync + Send>> {
+    use lsp_types::notification::Notification as _;
+    match &*notification.method {
+      DidOpenTextDocument::METHOD => {
+        let params: lsp_types::DidOpenTextDocumentParams = serde_json::from_value(notification.params)?;
+        self.update(params.text_document.uri, Some(params.text_document.text))
+      }
+      DidChangeTextDocument::METHOD => {
+        let mut params: lsp_types::DidChangeTextDocumentParams = serde_json::from_value(notification.params)?;
+        // the document is synced in full, so the last change has all of it
+        let text = params.content_changes.pop().map(|change| change.text);
+        self.update(params.text_document.uri, text)
+      }
+      DidCloseTextDocument::METHOD => {
+        let params: lsp_types::DidCloseTextDocumentParams = serde_json::from_value(notification.params)?;
+        let uri = params.text_document.uri;
+        if let Some(path) = canonical_path(&uri) {
+          self.docs.remove(&path);
+        }
+        self.analyses.remove(&uri);
+        self.publish(uri, vec![])
+      }
+      _ => Ok(()),
+    }
+  }
+
+  fn update(&mut self, uri: Url, text: Option<String>) -> Result<(), Box<dyn Error + Sync + Send>> {
+    if let (Some(path), Some(text)) = (canonical_path(&uri), text) {
+      self.docs.insert(path, text);
+    }
+    let mut uris = self.analyses.keys().filter(|&x| *x != uri).cloned().collect::<Vec<_>>();
+    uris.push(uri);
+    for uri in uris {
+      let Ok(path) = uri.to_file_path() else { continue };
+      let analysis = Analysis::new(&path, &CheckOptions::default(), &Overlay { base: FileSystem, files: &self.docs });
+      let diagnostics = self.diagnostics(&analysis, &path);
+      self.analyses.insert(uri.clone(), analysis);
+      self.publish(uri, diagnostics)?;
+    }
+    Ok(())
+  }
+
+  fn diagnostics(&self, analysis: &Analysis, path: &Path) -> Vec<lsp_types::Diagnostic> {
+    let root = path.display().to_string();
+    let mut diagnostics = vec![];
+    for diagnostic in analysis.diagnostics() {
+      let related = diagnostic.related.iter().filter_map(|(location, note)| {
+        Some(DiagnosticRelatedInformation { location: lsp_location(analysis, location)?, message: note.clone() })
+      });
+      let related = related.collect::<Vec<_>>();
+      // diagnostics in other files are shown at the top of this one, pointing
+      // to where they apply
+      let range = match &diagnostic.location {
+        Some(location) if location.file == root => lsp_range(analysis, location),
+        _ => None,
+      };
+      diagnostics.push(lsp_types::Diagnostic {
+        range: range.unwrap_or_default(),
+        severity: Some(match diagnostic.severity {
+          Severity::Error => DiagnosticSeverity::ERROR,
+          Severity::Warning => DiagnosticSeverity::WARNING,
+        }),
+        source: Some("inet-lifetimes".to_owned()),
+        message: diagnostic.message.clone(),
+        related_information: (!related.is_empty()).then_some(related),
+        ..Default::default()
+      });
+    }
+    diagnostics
+  }
+
+  fn publish(&self, uri: Url, diagnostics: Vec<lsp_types::Diagnostic>) -> Result<(), Box<dyn Error + Sync + Send>> {
+    use lsp_types::notification::Notification as _;
+    let params = PublishDiagnosticsParams { uri, diagnostics, version: None };
+    let notification = Notification::new(PublishDiagnostics::METHOD.to_owned(), params);
+    self.connection.sender.send(Message::Notification(notification))?;
+    Ok(())
+  }
+
+  fn handle_request(&mut self, request: Request) -> Result<(), Box<dyn Error + Sync + Send>> {
+    use lsp_types::request::Request as _;
+    let result = match &*request.method {
+      GotoDefinition::METHOD => {
+        let params: lsp_types::GotoDefinitionParams = serde_json::from_value(request.params)?;
+        let position = params.text_document_position_params;
+        self.at(&position.text_document.uri, position.position, |analysis, offset| {
+          let location = lsp_location(analysis, &analysis.definition(offset)?)?;
+          serde_json::to_value(GotoDefinitionResponse::Scalar(location)).ok()
+        })
+      }
+      HoverRequest::METHOD => {
+        let params: lsp_types::HoverParams = serde_json::from_value(request.params)?;
+        let position = params.text_document_position_params;
+        self.at(&position.text_document.uri, position.position, |analysis, offset| {
+          let value = format!("```inlt\n{}\n```", analysis.hover(offset)?);
+          let contents = HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value });
+          serde_json::to_value(Hover { contents, range: None }).ok()
+        })
+      }
+      DocumentSymbolRequest::METHOD => {
+        let params: lsp_types::DocumentSymbolParams = serde_json::from_value(request.params)?;
+        let symbols = self.analyses.get(&params.text_document.uri).map(|analysis| {
+          let symbols = analysis.symbols().into_iter().filter_map(|symbol| {
+            let range = lsp_range(analysis, &symbol.location)?;
+            #[allow(deprecated)]
+            Some(DocumentSymbol {
+              name: symbol.name,
+              detail: None,
+              kind: match symbol.kind {
+                SymbolKind::Type => lsp_types::SymbolKind::STRUCT,
+                SymbolKind::Agent => lsp_types::SymbolKind::CONSTRUCTOR,
+                SymbolKind::Rule => lsp_types::SymbolKind::METHOD,
+                SymbolKind::Net => lsp_types::SymbolKind::FUNCTION,
+              },
+              tags: None,
+              deprecated: None,
+              range,
+              selection_range: range,
+              children: None,
+            })
+          });
+          DocumentSymbolResponse::Nested(symbols.collect())
+        });
+        serde_json::to_value(symbols)?
+      }
+      _ => {
+        let message = format!("unsupported request `{}`", request.method);
+        let response = Response::new_err(request.id, lsp_server::ErrorCode::MethodNotFound as i32, message);
+        self.connection.sender.send(Message::Response(response))?;
+        return Ok(());
+      }
+    };
+    self.respond(request.id, result)
+  }
+
+  fn at(&self, uri: &Url, position: Position, query: impl FnOnce(&Analysis, usize) -> Option<Value>) -> Value {
+    let analysis = self.analyses.get(uri);
+    let result = analysis.and_then(|analysis| {
+      let path = uri.to_file_path().ok()?;
+      let offset = offset(analysis.source(&path.display().to_string())?, position);
+      query(analysis, offset)
+    });
+    result.unwrap_or(Value::Null)
+  }
+
+  fn respond(&self, id: RequestId, result: Value) -> Result<(), Box<dyn Error + Sync + Send>> {
+    self.connection.sender.send(Message::Response(Response::new_ok(id, result)))?;
+    Ok(())
+  }
+}
+
+fn canonical_path(uri: &Url) -> Option<PathBuf> {
+  uri.to_file_path().ok()?.canonicalize().ok()
+}
+
+fn lsp_location(analysis: &Analysis, location: &Location) -> Option<lsp_types::Location> {
+  let uri = Url::from_file_path(Path::new(&location.file).canonicalize().ok()?).ok()?;
+  Some(lsp_types::Location { uri, range: lsp_range(analysis, location)? })
+}
+
+fn lsp_range(analysis: &Analysis, location: &Location) -> Option<Range> {
+  let source = analysis.source(&location.file)?;
+  Some(Range { start: position(source, location.start), end: position(source, location.end) })
+}
+
+fn position(source: &str, offset: usize) -> Position {
+  let before = &source[..offset.min(source.len())];
+  let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+  let line = before.matches('\n').count() as u32;
+  Position { line, character: before[line_start..].encode_utf16().count() as u32 }
+}
+
+fn offset(source: &str, position: Position) -> usize {
+  let mut line_start = 0;
+  for _ in 0..position.line {
+    match source[line_start..].find('\n') {
+      Some(i) => line_start += i + 1,
+      None => return source.len(),
+    }
+  }
+  let mut units = 0;
+  for (i, c) in source[line_start..].char_indices() {
+    if units >= position.character as usize || c == '\n' {
+      return line_start + i;
+    }
+    units += c.len_utf16();
+  }
+  source.len()
+}
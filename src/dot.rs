@@ -0,0 +1,172 @@
+use std::fmt::Write;
+
+use crate::{
+  globals::{GlobalCtx, PortLabel, Type},
+  program::{NetDef, Node, Program, RuleDef},
+  vars::{Var, VarCtx},
+};
+
+impl Program {
+  /// Renders the net named `name`, or the rule for the components `A` and `B`
+  /// if `name` is `A-B`. If `lifetimes` is set, each end of an edge is labelled
+  /// with the lifetime of its port.
+  pub fn dot(&self, name: &str, lifetimes: bool) -> Option<String> {
+    let mut dot = Dot { globals: &self.globals, lifetimes, output: String::new() };
+    if let Some(net) = self.net(name) {
+      dot.net(net);
+    } else {
+      dot.rule(self.rules.iter().find(|rule| self.rule_name(rule) == name)?, name);
+    }
+    Some(dot.output)
+  }
+
+  /// Renders the lifetime orders of each agent, rule, and net, named as for
+  /// [`Program::dot`], with the lifetimes imported while checking it.
+  pub fn dot_orders(&self) -> Vec<(String, String)> {
+    let components = &self.globals.components;
+    let agents = self.agents.iter().map(|agent| (components.name(agent.id).to_owned(), &agent.lt_ctx));
+    let rules = self.rules.iter().map(|rule| (self.rule_name(rule), &rule.lt_ctx));
+    let nets = self.nets.iter().map(|net| (components.name(net.id).to_owned(), &net.lt_ctx));
+    agents
+      .chain(rules)
+      .chain(nets)
+      .map(|(name, lt_ctx)| {
+        let graph = lt_ctx.show_orders(&name);
+        (name, graph)
+      })
+      .collect()
+  }
+
+  fn rule_name(&self, rule: &RuleDef) -> String {
+    let components = &self.globals.components;
+    format!("{}-{}", components.name(rule.a.component), components.name(rule.b.component))
+  }
+}
+
+struct Dot<'p> {
+  globals: &'p GlobalCtx,
+  lifetimes: bool,
+  output: String,
+}
+
+struct End {
+  vertex: String,
+  principal: bool,
+  ty: Type,
+  lifetime: String,
+}
+
+impl Dot<'_> {
+  fn net(&mut self, net: &NetDef) {
+    let name = self.globals.components.name(net.id);
+    writeln!(self.output, "digraph \"{name}\" {{").unwrap();
+    let mut ends = vec![];
+    for (i, &(var, PortLabel(ty, lt, _))) in net.free_ports.iter().enumerate() {
+      let vertex = format!("free{i}");
+      writeln!(self.output, "  {vertex} [shape=plaintext, label=\"{}\"]", net.var_ctx.vars.name(var)).unwrap();
+      let lifetime = net.lt_ctx.lifetimes.name(lt).to_owned();
+      ends.push((var, End { vertex, principal: false, ty: !ty, lifetime }));
+    }
+    self.nodes("  ", "n", &net.nodes, &mut ends);
+    self.wires("  ", &net.var_ctx, ends);
+    self.output.push_str("}\n");
+  }
+
+  fn rule(&mut self, rule: &RuleDef, name: &str) {
+    writeln!(self.output, "digraph \"{name}\" {{").unwrap();
+
+    self.output.push_str("  subgraph cluster_redex {\n    label=\"redex\"\n");
+    let mut interface = vec![];
+    let mut redex_ends = vec![];
+    for (vertex, node) in [("a", &rule.a), ("b", &rule.b)] {
+      writeln!(self.output, "    {vertex} [label=\"{}\"]", self.globals.components.name(node.component)).unwrap();
+      let prefix = format!("{}.", self.globals.components.name(node.component));
+      for (i, (&var, end)) in node.ports.iter().zip(self.ends(vertex, &prefix, node)).enumerate() {
+        if i == 0 {
+          redex_ends.push((var, end));
+          continue;
+        }
+        // the port is exposed to the result, where it has the dual type
+        let exposed = format!("{vertex}{i}");
+        let var_name = rule.var_ctx.vars.name(var);
+        writeln!(self.output, "    redex_{exposed} [shape=plaintext, label=\"{var_name}\"]").unwrap();
+        let outer =
+          End { vertex: format!("redex_{exposed}"), principal: false, ty: !end.ty, lifetime: end.lifetime.clone() };
+        self.wire("    ", var_name, &end, &outer);
+        interface.push((var, exposed, outer.ty, outer.lifetime));
+      }
+    }
+    self.wires("    ", &rule.var_ctx, redex_ends);
+    self.output.push_str("  }\n");
+
+    self.output.push_str("  subgraph cluster_result {\n    label=\"result\"\n");
+    let mut ends = vec![];
+    for (var, exposed, ty, lifetime) in interface {
+      let vertex = format!("result_{exposed}");
+      writeln!(self.output, "    {vertex} [shape=plaintext, label=\"{}\"]", rule.var_ctx.vars.name(var)).unwrap();
+      ends.push((var, End { vertex, principal: false, ty, lifetime }));
+    }
+    self.nodes("    ", "n", &rule.result, &mut ends);
+    self.wires("    ", &rule.var_ctx, ends);
+    self.output.push_str("  }\n");
+
+    self.output.push_str("}\n");
+  }
+
+  fn nodes(&mut self, indent: &str, prefix: &str, nodes: &[Node], ends: &mut Vec<(Var, End)>) {
+    for (i, node) in nodes.iter().enumerate() {
+      let name = self.globals.components.name(node.component);
+      let vertex = format!("{prefix}{i}");
+      writeln!(self.output, "{indent}{vertex} [label=\"{name}\"]").unwrap();
+      ends.extend(node.ports.iter().copied().zip(self.ends(&vertex, &format!("{name}#{i}."), node)));
+    }
+  }
+
+  fn ends(&self, vertex: &str, prefix: &str, node: &Node) -> Vec<End> {
+    let component = &self.globals.components[node.component];
+    let ports = component.ports.iter().enumerate().take(node.ports.len());
+    ports
+      .map(|(i, &PortLabel(ty, lt, _))| End {
+        vertex: vertex.to_owned(),
+        principal: i == 0,
+        ty,
+        lifetime: format!("'{prefix}{}", &component.lt_ctx.lifetimes.name(lt)[1..]),
+      })
+      .collect()
+  }
+
+  fn wires(&mut self, indent: &str, var_ctx: &VarCtx, ends: Vec<(Var, End)>) {
+    let mut by_var: Vec<(Var, Vec<End>)> = vec![];
+    for (var, end) in ends {
+      match by_var.iter_mut().find(|(x, _)| *x == var) {
+        Some((_, ends)) => ends.push(end),
+        None => by_var.push((var, vec![end])),
+      }
+    }
+    for (var, ends) in by_var {
+      if let [a, b, ..] = &ends[..] {
+        self.wire(indent, var_ctx.vars.name(var), a, b);
+      }
+    }
+  }
+
+  fn wire(&mut self, indent: &str, var: &str, a: &End, b: &End) {
+    let types = &self.globals.types;
+    let (a, b) = if types.name(a.ty).starts_with('!') { (b, a) } else { (a, b) };
+    let arrow = |end: &End| if end.principal { "dot" } else { "none" };
+    write!(
+      self.output,
+      "{indent}{} -> {} [label=\"{var}: {}\", dir=both, arrowtail={}, arrowhead={}",
+      a.vertex,
+      b.vertex,
+      types.name(a.ty),
+      arrow(a),
+      arrow(b),
+    )
+    .unwrap();
+    if self.lifetimes {
+      write!(self.output, ", taillabel=\"{}\", headlabel=\"{}\"", a.lifetime, b.lifetime).unwrap();
+    }
+    self.output.push_str("]\n");
+  }
+}
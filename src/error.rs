@@ -1,34 +1,253 @@
 use std::fmt::{self, Display, Write};
 
-use crate::util::Captures;
+use serde_json::json;
 
-pub struct Error(String, ErrorGroup);
+use crate::{
+  span::{Files, Span},
+  util::{Captures, DisplayFn},
+};
+
+/// A diagnostic: a message, optionally located at a span of the source, with
+/// labelled secondary spans, suggested fixes, and nested diagnostics.
+///
+/// A report of several diagnostics is an error whose message is a heading,
+/// such as `check errors:`, and whose children are the diagnostics.
+#[derive(Debug)]
+pub struct Error {
+  message: String,
+  span: Option<Span>,
+  labels: Vec<(Span, Label)>,
+  children: ErrorGroup,
+}
+
+#[derive(Debug)]
+enum Label {
+  Note(String),
+  Fix(String),
+}
 
 impl Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    self.fmt(f, 1)
+    self.fmt(f, None, 1)
   }
 }
 
+impl std::error::Error for Error {}
+
 impl Error {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
-    f.write_str(&self.0)?;
-    for suberror in &self.1 .0 {
-      f.write_char('\n')?;
-      for _ in 0..indent {
-        f.write_str("  ")?;
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+
+  /// The primary span of this error, if it has one.
+  pub fn span(&self) -> Option<Span> {
+    self.span
+  }
+
+  /// The secondary spans of this error, each with its label.
+  pub fn labels(&self) -> impl Iterator<Item = (Span, &str)> {
+    self.labels.iter().filter_map(|(span, label)| match label {
+      Label::Note(note) => Some((*span, &**note)),
+      Label::Fix(_) => None,
+    })
+  }
+
+  /// The errors nested in this one, such as the diagnostics of a report or
+  /// the notes of a diagnostic.
+  pub fn children(&self) -> &[Error] {
+    &self.children.0
+  }
+
+  fn fmt(&self, f: &mut fmt::Formatter<'_>, files: Option<&Files>, indent: usize) -> fmt::Result {
+    // the lines of a message are aligned with its first
+    for (i, line) in self.message.lines().enumerate() {
+      if i != 0 {
+        newline(f, indent - 1)?;
+      }
+      f.write_str(line)?;
+    }
+    if let Some(files) = files {
+      if let Some(span) = self.span {
+        Self::fmt_span(f, files, span, indent)?;
+      }
+      for (span, label) in &self.labels {
+        if let Label::Note(label) = label {
+          newline(f, indent)?;
+          f.write_str(label)?;
+          Self::fmt_span(f, files, *span, indent + 1)?;
+        }
       }
-      suberror.fmt(f, indent + 1)?;
+    }
+    for suberror in &self.children.0 {
+      newline(f, indent)?;
+      suberror.fmt(f, files, indent + 1)?;
+    }
+    if let Some(files) = files {
+      for (span, label) in &self.labels {
+        let Label::Fix(replacement) = label else { continue };
+        newline(f, indent)?;
+        write!(f, "help: replace with `{replacement}`")?;
+        Self::fmt_span(f, files, *span, indent + 1)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn fmt_span(f: &mut fmt::Formatter<'_>, files: &Files, span: Span, indent: usize) -> fmt::Result {
+    let file = &files[span.file];
+    let (line, col) = file.line_col(span.start);
+    newline(f, indent)?;
+    write!(f, "--> {}:{line}:{col}", file.name)?;
+    for line in file.highlight(span).lines() {
+      newline(f, indent)?;
+      f.write_str(line)?;
     }
     Ok(())
   }
 
+  /// Splits this report into standalone diagnostics, one for each error in
+  /// the tree that has a span, or that has no children. The message of each
+  /// diagnostic includes the messages of the errors containing it.
+  pub fn locate(&self) -> Vec<Located> {
+    let mut located = vec![];
+    for child in &self.children.0 {
+      child.locate_into(String::new(), &mut located);
+    }
+    located
+  }
+
+  fn locate_into(&self, context: String, located: &mut Vec<Located>) {
+    let span = self.span.or(self.labels.iter().find(|(_, label)| matches!(label, Label::Note(_))).map(|x| x.0));
+    if span.is_none() && !self.children.0.is_empty() {
+      for child in &self.children.0 {
+        child.locate_into(format!("{context}{}\n", self.message), located);
+      }
+      return;
+    }
+    let mut related = vec![];
+    self.collect_notes(&mut related);
+    located.push(Located { span, message: format!("{context}{self}"), related });
+  }
+
+  fn collect_notes(&self, notes: &mut Vec<(Span, String)>) {
+    for (span, label) in &self.labels {
+      if let Label::Note(note) = label {
+        notes.push((*span, note.clone()));
+      }
+    }
+    for child in &self.children.0 {
+      child.collect_notes(notes);
+    }
+  }
+
+  /// Displays each child of this error, a report of diagnostics, as a line of
+  /// JSON with the given `severity`.
+  pub fn show_json_lines<'a>(&'a self, files: &'a Files, severity: &'a str) -> impl Display + 'a {
+    DisplayFn(move |f| {
+      for (i, child) in self.children.0.iter().enumerate() {
+        write!(f, "{}{}", if i == 0 { "" } else { "\n" }, child.to_json(files, severity))?;
+      }
+      Ok(())
+    })
+  }
+
+  fn to_json(&self, files: &Files, severity: &str) -> serde_json::Value {
+    let span_json = |span: Span| {
+      let file = &files[span.file];
+      let (line, col) = file.line_col(span.start);
+      let (end_line, end_col) = file.line_col(span.end);
+      json!({
+        "file": file.name,
+        "start": { "offset": span.start, "line": line, "col": col },
+        "end": { "offset": span.end, "line": end_line, "col": end_col },
+      })
+    };
+    let (mut related, mut fixes) = (vec![], vec![]);
+    for &(span, ref label) in &self.labels {
+      match label {
+        Label::Note(message) => related.push(json!({ "message": message, "span": span_json(span) })),
+        Label::Fix(replacement) => fixes.push(json!({ "replacement": replacement, "span": span_json(span) })),
+      }
+    }
+    json!({
+      "message": self.message,
+      "severity": severity,
+      "file": self.span.map(|span| &files[span.file].name),
+      "span": self.span.map(span_json),
+      "related": related,
+      "fixes": fixes,
+      "children": self.children.0.iter().map(|child| child.to_json(files, severity)).collect::<Vec<_>>(),
+    })
+  }
+
+  /// Displays this error, highlighting the source of each span.
+  pub fn show<'a>(&'a self, files: &'a Files) -> impl Display + 'a {
+    DisplayFn(move |f| self.fmt(f, Some(files), 1))
+  }
+
+  /// Sets the primary span of this error, if it does not already have one.
+  pub fn at(mut self, span: impl Into<Option<Span>>) -> Self {
+    self.span = self.span.or(span.into());
+    self
+  }
+
+  /// Attaches a secondary span to this error, described by `label`.
+  pub fn label(mut self, span: Span, label: impl Display) -> Self {
+    self.labels.push((span, Label::Note(label.to_string())));
+    self
+  }
+
+  /// Attaches a note to this error, displayed after its labels.
+  pub fn note(mut self, note: impl Display) -> Self {
+    self.children.push(Error::from(note.to_string()));
+    self
+  }
+
+  /// Suggests replacing the source at `span` with `replacement` to fix this
+  /// error.
+  pub fn fix(mut self, span: Span, replacement: impl Display) -> Self {
+    self.labels.push((span, Label::Fix(replacement.to_string())));
+    self
+  }
+
+  /// Collects the suggested fixes of this error and its children.
+  pub fn fixes(&self) -> Vec<(Span, String)> {
+    let mut fixes = self
+      .labels
+      .iter()
+      .filter_map(|(span, label)| match label {
+        Label::Fix(replacement) => Some((*span, replacement.clone())),
+        Label::Note(_) => None,
+      })
+      .collect::<Vec<_>>();
+    for child in &self.children.0 {
+      fixes.extend(child.fixes());
+    }
+    fixes
+  }
+
   pub fn context<'a>(ctx: impl Display + 'a) -> impl (FnOnce(Error) -> Error) + Captures<&'a ()> {
-    move |err| Error(ctx.to_string(), ErrorGroup(vec![err]))
+    move |err| Error { children: ErrorGroup(vec![err]), ..Error::from(ctx.to_string()) }
   }
 }
 
-#[derive(Default)]
+/// A standalone diagnostic, split from a report by [`Error::locate`].
+pub struct Located {
+  pub span: Option<Span>,
+  pub message: String,
+  pub related: Vec<(Span, String)>,
+}
+
+fn newline(f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+  f.write_char('\n')?;
+  for _ in 0..indent {
+    f.write_str("  ")?;
+  }
+  Ok(())
+}
+
+/// Errors collected while loading or checking, to be reported together.
+#[derive(Debug, Default)]
 pub struct ErrorGroup(Vec<Error>);
 
 pub trait Pushable {
@@ -71,11 +290,15 @@ impl ErrorGroup {
     value.push_to(self)
   }
 
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
   pub fn report(&mut self, label: impl Display) -> Result<(), Error> {
     if self.0.is_empty() {
       Ok(())
     } else {
-      Err(Error(label.to_string(), std::mem::take(self)))
+      Err(Error { children: std::mem::take(self), ..Error::from(label.to_string()) })
     }
   }
 }
@@ -93,8 +316,8 @@ impl<T: ToError> From<T> for Error {
 }
 
 impl From<String> for Error {
-  fn from(value: String) -> Self {
-    Error(value, ErrorGroup::default())
+  fn from(message: String) -> Self {
+    Error { message, span: None, labels: Vec::new(), children: ErrorGroup::default() }
   }
 }
 
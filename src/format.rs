@@ -0,0 +1,331 @@
+use std::fmt::Write;
+
+use crate::syntax::{
+  self, Item, LtCtx, LtPart, NodeLike, Port, PortLabel, SyntaxError, Token, Tokens, Trivia, TriviaKind, TypeRef,
+};
+
+pub fn format(input: &str) -> Result<String, Vec<SyntaxError>> {
+  let (syntax, errors) = syntax::parse(input);
+  if !errors.is_empty() {
+    return Err(errors);
+  }
+  let mut formatter = Formatter::default();
+  let mut chunks: Vec<Chunk> = vec![];
+  for item in &syntax.items {
+    formatter.trivia(&item.first().leading);
+    let (trailing, comments) = formatter.take_comments();
+    if let Some(prev) = chunks.last_mut() {
+      prev.set_trailing(trailing);
+    }
+    let blank = formatter.newlines > 1;
+    let mut chunk = formatter.item(item);
+    chunk.comments = comments.into_iter().chain(chunk.comments).collect();
+    chunk.blank = blank;
+    chunks.push(chunk);
+  }
+  formatter.trivia(&syntax.end);
+  let (trailing, comments) = formatter.take_comments();
+  if let Some(prev) = chunks.last_mut() {
+    prev.set_trailing(trailing);
+  }
+
+  let mut output = String::new();
+  let mut ctx_width = 0;
+  for (i, chunk) in chunks.iter().enumerate() {
+    if i != 0 && chunk.blank_before() {
+      output.push('\n');
+    }
+    if !chunk.in_group(i.checked_sub(1).map(|i| &chunks[i])) {
+      // the widest context among the agents declared in a row with this one
+      let group = chunks[i..].iter().enumerate().take_while(|&(j, x)| j == 0 || x.in_group(Some(&chunks[i + j - 1])));
+      ctx_width = group.filter_map(|(_, x)| x.agent_ctx()).map(str::len).max().unwrap_or(0);
+    }
+    chunk.write(&mut output, "", ctx_width);
+  }
+  write_comments(&mut output, "", &comments, !chunks.is_empty());
+  Ok(output)
+}
+
+#[derive(Default)]
+struct Formatter<'i> {
+  newlines: usize,
+  started: bool,
+  comments: Vec<Comment<'i>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Comment<'i> {
+  text: &'i str,
+  trailing: bool,
+  blank: bool,
+}
+
+#[derive(Default)]
+struct Chunk<'i> {
+  comments: Vec<Comment<'i>>,
+  blank: bool,
+  agent_ctx: Option<String>,
+  line: String,
+  trailing: Option<&'i str>,
+  body: Option<Body<'i>>,
+}
+
+struct Body<'i> {
+  nodes: Vec<Chunk<'i>>,
+  comments: Vec<Comment<'i>>,
+  trailing: Option<&'i str>,
+}
+
+impl<'i> Chunk<'i> {
+  fn blank_before(&self) -> bool {
+    self.comments.first().map_or(self.blank, |x| x.blank)
+  }
+
+  fn agent_ctx(&self) -> Option<&str> {
+    self.agent_ctx.as_deref()
+  }
+
+  fn in_group(&self, prev: Option<&Chunk>) -> bool {
+    prev.is_some_and(|prev| prev.agent_ctx.is_some())
+      && self.agent_ctx.is_some()
+      && self.comments.is_empty()
+      && !self.blank
+  }
+
+  fn set_trailing(&mut self, trailing: Option<Comment<'i>>) {
+    if let Some(trailing) = trailing {
+      match &mut self.body {
+        Some(body) => body.trailing = Some(trailing.text),
+        None => self.trailing = Some(trailing.text),
+      }
+    }
+  }
+
+  fn write(&self, output: &mut String, indent: &str, ctx_width: usize) {
+    write_comments(output, indent, &self.comments, false);
+    if !self.comments.is_empty() && self.blank {
+      output.push('\n');
+    }
+    output.push_str(indent);
+    if let Some(ctx) = &self.agent_ctx {
+      write!(output, "{ctx:ctx_width$} ").unwrap();
+    }
+    output.push_str(&self.line);
+    let Some(body) = &self.body else {
+      write_trailing(output, self.trailing);
+      return;
+    };
+    if body.nodes.is_empty() && body.comments.is_empty() && self.trailing.is_none() {
+      output.push_str(" {}");
+      write_trailing(output, body.trailing);
+      return;
+    }
+    output.push_str(" {");
+    write_trailing(output, self.trailing);
+    let inner = format!("{indent}  ");
+    for (i, node) in body.nodes.iter().enumerate() {
+      if i != 0 && node.blank_before() {
+        output.push('\n');
+      }
+      node.write(output, &inner, 0);
+    }
+    write_comments(output, &inner, &body.comments, !body.nodes.is_empty());
+    write!(output, "{indent}}}").unwrap();
+    write_trailing(output, body.trailing);
+  }
+}
+
+fn write_comments(output: &mut String, indent: &str, comments: &[Comment], after_line: bool) {
+  for (i, comment) in comments.iter().enumerate() {
+    if comment.blank && (i != 0 || after_line) {
+      output.push('\n');
+    }
+    writeln!(output, "{indent}{}", comment.text).unwrap();
+  }
+}
+
+fn write_trailing(output: &mut String, trailing: Option<&str>) {
+  if let Some(trailing) = trailing {
+    write!(output, " {trailing}").unwrap();
+  }
+  output.push('\n');
+}
+
+impl<'i> Formatter<'i> {
+  fn trivia(&mut self, trivia: &[Trivia<'i>]) {
+    self.newlines = 0;
+    for trivia in trivia {
+      match trivia.kind {
+        TriviaKind::Whitespace | TriviaKind::Skipped => self.newlines += trivia.text.matches('\n').count(),
+        TriviaKind::Comment => {
+          let trailing = self.newlines == 0 && self.started;
+          self.comments.push(Comment { text: trivia.text.trim_end(), trailing, blank: self.newlines > 1 });
+          self.newlines = 0;
+        }
+      }
+    }
+    self.started = true;
+  }
+
+  fn tokens(&mut self, syntax: &impl Tokens<'i>) {
+    syntax.tokens(&mut |token| self.trivia(&token.leading));
+  }
+
+  fn take_comments(&mut self) -> (Option<Comment<'i>>, Vec<Comment<'i>>) {
+    let mut comments = std::mem::take(&mut self.comments);
+    let trailing = comments.first().is_some_and(|x| x.trailing).then(|| comments.remove(0));
+    (trailing, comments)
+  }
+
+  fn item(&mut self, item: &Item<'i>) -> Chunk<'i> {
+    let mut chunk = Chunk::default();
+    if item.first() != item.keyword() {
+      self.tokens(item.keyword());
+    }
+    match item {
+      Item::Include(item) => {
+        self.tokens(&item.path);
+        chunk.line = format!("include {}", item.path.text);
+      }
+      Item::Use(item) => {
+        self.tokens(&item.module);
+        self.tokens(&item.colons);
+        let mut names = vec![];
+        for (name, comma) in &item.names {
+          self.tokens(&name.name);
+          match &name.alias {
+            Some((as_, alias)) => {
+              self.tokens(as_);
+              self.tokens(alias);
+              names.push(format!("{} as {}", name.name.text, alias.text));
+            }
+            None => names.push(name.name.text.to_owned()),
+          }
+          self.tokens(comma);
+        }
+        let names = match &item.braces {
+          Some((open, close)) => {
+            self.tokens(open);
+            self.tokens(close);
+            format!("{{{}}}", names.join(", "))
+          }
+          None => names.join(", "),
+        };
+        chunk.line = format!("use {}::{names}", item.module.text);
+      }
+      Item::Type(item) => {
+        self.tokens(&item.ty);
+        self.tokens(&item.colon);
+        self.tokens(&item.polarity);
+        chunk.line = format!("{}type {}: {}", show_vis(&item.vis), show_type(&item.ty), item.polarity.text);
+      }
+      Item::Agent(item) => {
+        self.tokens(&item.lt_ctx);
+        self.tokens(&item.node);
+        let ctx = item.lt_ctx.as_ref().map(show_lt_ctx).unwrap_or_default();
+        chunk.agent_ctx = Some(format!("{}agent{ctx}", show_vis(&item.vis)));
+        chunk.line =
+          show_node_like(&item.node, |port| format!("{}{}", show_port_name(&port.name), show_port_label(&port.label)));
+      }
+      Item::Rule(item) => {
+        self.tokens(&item.a);
+        self.tokens(&item.b);
+        let (a, b) = (show_node(&item.a), show_node(&item.b));
+        chunk.line = format!("rule {a} {b}");
+        self.body(&mut chunk, &item.body);
+      }
+      Item::Net(item) => {
+        self.tokens(&item.lt_ctx);
+        self.tokens(&item.node);
+        let ctx = item.lt_ctx.as_ref().map(show_lt_ctx).unwrap_or_default();
+        let node = show_node_like(&item.node, |port| format!("{}: {}", port.var.text, show_port_label(&port.label)));
+        chunk.line = format!("{}net{ctx} {node}", show_vis(&item.vis));
+        self.body(&mut chunk, &item.body);
+      }
+    }
+    // comments within the item are moved before it
+    chunk.comments.append(&mut self.comments);
+    chunk
+  }
+
+  fn body(&mut self, chunk: &mut Chunk<'i>, body: &syntax::Body<'i>) {
+    self.tokens(&body.open);
+    chunk.comments.append(&mut self.comments);
+    let mut nodes: Vec<Chunk> = vec![];
+    let mut attach = |nodes: &mut Vec<Chunk<'i>>, trailing: Option<Comment<'i>>| match nodes.last_mut() {
+      Some(prev) => prev.set_trailing(trailing),
+      None => chunk.trailing = trailing.map(|x| x.text),
+    };
+    for node in &body.nodes {
+      self.trivia(&node.name.leading);
+      let (trailing, mut comments) = self.take_comments();
+      attach(&mut nodes, trailing);
+      let blank = self.newlines > 1;
+      self.tokens(&node.open);
+      for (port, comma) in &node.ports {
+        self.tokens(port);
+        self.tokens(comma);
+      }
+      self.tokens(&node.close);
+      comments.append(&mut self.comments);
+      nodes.push(Chunk { comments, blank, line: show_node(node), ..Chunk::default() });
+    }
+    self.trivia(&body.close.leading);
+    let (trailing, comments) = self.take_comments();
+    attach(&mut nodes, trailing);
+    chunk.body = Some(Body { nodes, comments, trailing: None });
+  }
+}
+
+fn show_vis(vis: &Option<Token>) -> &'static str {
+  if vis.is_some() {
+    "pub "
+  } else {
+    ""
+  }
+}
+
+fn show_node(node: &syntax::Node) -> String {
+  show_node_like(node, |arg| match &arg.port {
+    Port::Var(var) => format!("{}{}", show_port_name(&arg.name), var.text),
+    Port::Node(node) => format!("{}{}", show_port_name(&arg.name), show_node(node)),
+  })
+}
+
+fn show_port_name(name: &Option<(Token, Token)>) -> String {
+  name.as_ref().map(|(name, _)| format!("{}: ", name.text)).unwrap_or_default()
+}
+
+fn show_node_like<T>(node: &NodeLike<T>, show_port: impl Fn(&T) -> String) -> String {
+  let ports = node.ports.iter().map(|(port, _)| show_port(port)).collect::<Vec<_>>();
+  format!("{}({})", node.name.text, ports.join(", "))
+}
+
+fn show_port_label(label: &PortLabel) -> String {
+  format!("{}{}", show_type(&label.ty), label.lt.text)
+}
+
+fn show_type(ty: &TypeRef) -> String {
+  format!("{}{}", if ty.bang.is_some() { "!" } else { "" }, ty.name.text)
+}
+
+fn show_lt_ctx(ctx: &LtCtx) -> String {
+  let mut output = String::from("[");
+  for (i, part) in ctx.parts.iter().enumerate() {
+    match part {
+      LtPart::Hole(hole) => output.push_str(hole.text),
+      LtPart::Decl(decl) => {
+        output.push_str(decl.lt.text);
+        if decl.question.is_some() {
+          output.push('?');
+        }
+      }
+      LtPart::Op(op) if op.text == "," => output.push_str(", "),
+      LtPart::Op(op) if op.text == "|" && i == 0 => output.push_str("| "),
+      LtPart::Op(op) if op.text == "|" && i == ctx.parts.len() - 1 => {}
+      LtPart::Op(op) => write!(output, " {} ", op.text).unwrap(),
+    }
+  }
+  output.push(']');
+  output
+}
@@ -1,16 +1,42 @@
 use crate::{
+  index_vec::IndexVec,
   lifetimes::{Lifetime, LifetimeCtx},
   new_index,
   order::Order,
   scope::Scope,
+  span::{FileId, Span},
+  util::DisplayFn,
+};
+use std::{
+  fmt::{Debug, Display},
+  ops::Not,
 };
-use std::{fmt::Debug, ops::Not};
 
+/// The types and components of a program, by name, and the modules they
+/// belong to.
 #[derive(Debug, Clone, Default)]
 pub struct GlobalCtx {
   pub type_order: Order<Type>,
   pub types: Scope<Type, TypeInfo>,
   pub components: Scope<Component, ComponentInfo>,
+  pub modules: IndexVec<Module, ModuleInfo>,
+  /// The module that declares each type, or that refers to it if it is
+  /// undeclared.
+  pub type_modules: IndexVec<Type, Module>,
+  /// The module that declares each component, or that refers to it if it is
+  /// undeclared.
+  pub component_modules: IndexVec<Component, Module>,
+}
+
+new_index!(pub Module "module");
+
+/// A source file, whose types and components are referred to from other files
+/// as `name::Item`.
+#[derive(Debug, Clone)]
+pub struct ModuleInfo {
+  /// The name of the file, without its directory or extension.
+  pub name: String,
+  pub file: FileId,
 }
 
 new_index!(pub Type "type");
@@ -26,10 +52,62 @@ new_index!(pub Component "component");
 pub struct ComponentInfo {
   pub lt_ctx: LifetimeCtx,
   pub ports: Vec<PortLabel>,
+  /// The name of each port, if it has one; the ports of a net are named by
+  /// their variables.
+  pub port_names: Vec<Option<String>>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct PortLabel(pub Type, pub Lifetime);
+pub struct PortLabel(pub Type, pub Lifetime, pub Span);
+
+impl GlobalCtx {
+  /// Whether `path`, which may be qualified by a module as in `nat::Succ`,
+  /// names the component `id`.
+  pub fn component_matches(&self, id: Component, path: &str) -> bool {
+    let module = &self.modules[self.component_modules[id]].name;
+    match path.rsplit_once("::") {
+      Some((qualifier, name)) => qualifier == module && name == self.components.name(id),
+      None => path == self.components.name(id),
+    }
+  }
+
+  /// Displays the header of a definition of the net `id`, with its contract,
+  /// naming the free port `i` with `port_name(i)`.
+  pub fn show_net_header<'a, D: Display>(
+    &'a self,
+    id: Component,
+    port_name: impl Fn(usize) -> D + 'a,
+  ) -> impl Display + 'a {
+    self.show_header("net", id, move |i| Some(port_name(i)))
+  }
+
+  /// Displays the header of a definition of the agent `id`, with its
+  /// contract.
+  pub fn show_agent_header(&self, id: Component) -> impl Display + '_ {
+    self.show_header("agent", id, move |i| self.components[id].port_names[i].as_deref())
+  }
+
+  fn show_header<'a, D: Display>(
+    &'a self,
+    keyword: &'a str,
+    id: Component,
+    port_name: impl Fn(usize) -> Option<D> + 'a,
+  ) -> impl Display + 'a {
+    DisplayFn(move |f| {
+      let info = &self.components[id];
+      write!(f, "{keyword}{} {}(", info.lt_ctx.show_decls(), self.components.name(id))?;
+      for (i, label) in info.ports.iter().enumerate() {
+        let (ty, lt) = (self.types.name(label.0), info.lt_ctx.lifetimes.name(label.1));
+        write!(f, "{}", if i == 0 { "" } else { ", " })?;
+        if let Some(name) = port_name(i) {
+          write!(f, "{name}: ")?;
+        }
+        write!(f, "{ty}{lt}")?;
+      }
+      write!(f, ")")
+    })
+  }
+}
 
 impl Not for Type {
   type Output = Type;
@@ -62,6 +140,12 @@ impl Debug for Component {
   }
 }
 
+impl Debug for Module {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "M{}", self.0)
+  }
+}
+
 impl Debug for PortLabel {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     write!(f, "{:?}{:?}", self.0, self.1)
@@ -18,7 +18,7 @@ impl Idx for usize {
 #[macro_export]
 macro_rules! new_index {
   ($vis:vis $Index:ident $kind:literal) => {
-    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
     $vis struct $Index(pub usize);
 
     impl From<usize> for $Index {
@@ -27,9 +27,9 @@ macro_rules! new_index {
       }
     }
 
-    impl Into<usize> for $Index {
-      fn into(self) -> usize {
-        self.0
+    impl From<$Index> for usize {
+      fn from(index: $Index) -> usize {
+        index.0
       }
     }
 
@@ -64,9 +64,6 @@ impl<I: Idx, T> IndexVec<I, T> {
   pub fn get_mut(&mut self, index: I) -> Option<&mut T> {
     self.vec.get_mut(index.into())
   }
-  pub fn into_iter(self) -> impl Iterator<Item = (I, T)> {
-    self.vec.into_iter().enumerate().map(|(index, value)| (index.into(), value))
-  }
   pub fn iter(&self) -> impl Iterator<Item = (I, &T)> {
     self.vec.iter().enumerate().map(|(index, value)| (index.into(), value))
   }
@@ -113,7 +110,7 @@ impl<I: Idx, T> IntoIterator for IndexVec<I, T> {
   type IntoIter = impl Iterator<Item = (I, T)>;
 
   fn into_iter(self) -> Self::IntoIter {
-    self.into_iter()
+    self.vec.into_iter().enumerate().map(|(index, value)| (index.into(), value))
   }
 }
 
@@ -1,46 +1,272 @@
-#![feature(impl_trait_in_assoc_type, impl_trait_in_fn_trait_return, const_option)]
+#![feature(impl_trait_in_assoc_type, impl_trait_in_fn_trait_return)]
 
-use std::{
-  collections::HashSet,
-  fs,
-  path::{Path, PathBuf},
+use std::{fmt, io, path::Path};
+
+pub mod analysis;
+mod dot;
+pub mod error;
+mod format;
+pub mod globals;
+pub mod index_vec;
+pub mod lifetimes;
+mod load;
+mod lower;
+pub mod order;
+pub mod program;
+pub mod resolve;
+mod runtime;
+pub mod scope;
+pub mod span;
+pub mod syntax;
+mod util;
+pub mod vars;
+
+use self::{
+  error::{Error, ErrorGroup},
+  load::load,
+  program::{CheckOptions, Program},
+  resolve::{FileSystem, Resolver},
+  runtime::Runtime,
+  span::Files,
 };
 
-use typed_arena::Arena;
+/// A program loaded and checked by [`check_file`] or [`check_source`].
+#[derive(Debug)]
+pub struct Checked {
+  pub program: Program,
+  /// The files the program was loaded from, which its spans refer to.
+  pub files: Files,
+  /// A report of the warnings found while checking, if there are any.
+  pub warnings: Option<Error>,
+}
 
-mod error;
-mod globals;
-mod index_vec;
-mod lifetimes;
-mod order;
-mod parser;
-mod program;
-mod scope;
-mod util;
-mod vars;
-
-use self::{parser::Parser, program::Program};
-
-fn load(initial_path: impl AsRef<Path>) -> Result<Program, String> {
-  let file_contents = Arena::<String>::new();
-  let mut seen_files = HashSet::<PathBuf>::new();
-  let mut parser = Parser::default();
-  let mut todo_files = vec![initial_path.as_ref().canonicalize().unwrap()];
-  while let Some(path) = todo_files.pop() {
-    let file = file_contents.alloc(String::from_utf8(fs::read(&path).unwrap()).unwrap());
-    parser.parse_file(file, |relative| {
-      let path = path.parent().unwrap().join(relative).canonicalize().unwrap();
-      if seen_files.insert(path.clone()) {
-        todo_files.push(path);
-      }
-    })?;
+/// A report of the errors that stopped a program from loading or checking,
+/// along with the files its spans refer to.
+#[derive(Debug)]
+pub struct Failure {
+  pub errors: Box<Error>,
+  pub files: Files,
+}
+
+impl Failure {
+  fn new(errors: impl Into<Error>, files: Files) -> Self {
+    Failure { errors: Box::new(errors.into()), files }
   }
 
-  Ok(parser.finish())
+  /// Displays each error of the report as a line of JSON, like the warnings
+  /// of [`check_json`].
+  pub fn show_json_lines(&self) -> impl fmt::Display + '_ {
+    self.errors.show_json_lines(&self.files, "error")
+  }
 }
 
-pub fn check(path: impl AsRef<Path>) -> Result<(), String> {
-  let mut program: Program = load(path)?;
-  program.check().report("check errors:").map_err(|x| x.to_string())?;
-  Ok(())
+impl fmt::Display for Failure {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.errors.show(&self.files))
+  }
+}
+
+impl std::error::Error for Failure {}
+
+/// Loads the program at `path` and the files it includes from the file
+/// system, and checks it.
+pub fn check_file(path: impl AsRef<Path>, options: &CheckOptions) -> Result<Checked, Failure> {
+  check_file_with(path, options, &FileSystem)
+}
+
+/// Loads the program at `path` and the files it includes with `resolver`, and
+/// checks it.
+pub fn check_file_with(
+  path: impl AsRef<Path>,
+  options: &CheckOptions,
+  resolver: &dyn Resolver,
+) -> Result<Checked, Failure> {
+  let mut files = Files::default();
+  let loaded = load(path, &mut files, resolver);
+  finish_check(loaded, files, options)
+}
+
+/// Loads a program from `source`, as the contents of a file named `name`, and
+/// checks it. The file need not exist; the files it includes are read with
+/// `resolver`, relative to `name`.
+pub fn check_source(
+  name: &str,
+  source: &str,
+  options: &CheckOptions,
+  resolver: &dyn Resolver,
+) -> Result<Checked, Failure> {
+  let mut files = Files::default();
+  let loaded = load::load_source(name, source.to_owned(), &mut files, resolver);
+  finish_check(loaded, files, options)
+}
+
+fn finish_check(
+  loaded: Result<(Program, ErrorGroup), Error>,
+  files: Files,
+  options: &CheckOptions,
+) -> Result<Checked, Failure> {
+  let (mut program, mut errors) = match loaded {
+    Ok(loaded) => loaded,
+    Err(errors) => return Err(Failure::new(errors, files)),
+  };
+  let mut warnings = ErrorGroup::default();
+  let check_errors = program.check(options, &mut warnings);
+  let label = match (errors.is_empty(), check_errors.is_empty()) {
+    (true, _) => "check errors:",
+    (false, true) => "syntax errors:",
+    (false, false) => "syntax and check errors:",
+  };
+  errors.push(check_errors);
+  if let Err(errors) = errors.report(label) {
+    return Err(Failure::new(errors, files));
+  }
+  Ok(Checked { program, files, warnings: warnings.report("check warnings:").err() })
+}
+
+/// Loads the program at `path` with `resolver`, failing if it has syntax
+/// errors.
+fn load_parsed(path: impl AsRef<Path>, resolver: &dyn Resolver) -> Result<(Program, Files), Failure> {
+  let mut files = Files::default();
+  let loaded = load(path, &mut files, resolver);
+  match loaded.and_then(|(program, mut errors)| errors.report("syntax errors:").map(|()| program)) {
+    Ok(program) => Ok((program, files)),
+    Err(errors) => Err(Failure::new(errors, files)),
+  }
+}
+
+/// Checks the program at `path`, reading it with `resolver`, and displays any
+/// warnings.
+pub fn check(
+  path: impl AsRef<Path>,
+  options: &CheckOptions,
+  resolver: &dyn Resolver,
+) -> Result<Option<String>, Failure> {
+  let checked = check_file_with(path, options, resolver)?;
+  Ok(checked.warnings.map(|warnings| warnings.show(&checked.files).to_string()))
+}
+
+/// Checks the program at `path` like [`check`], but displays each warning as
+/// a line of JSON, with its message, severity, location, related spans,
+/// suggested fixes, and children. Errors can be displayed likewise with
+/// [`Failure::show_json_lines`].
+pub fn check_json(
+  path: impl AsRef<Path>,
+  options: &CheckOptions,
+  resolver: &dyn Resolver,
+) -> Result<Option<String>, Failure> {
+  let checked = check_file_with(path, options, resolver)?;
+  Ok(checked.warnings.map(|warnings| warnings.show_json_lines(&checked.files, "warning").to_string()))
+}
+
+/// Checks the program at `path`, then reduces the net named `net` to normal
+/// form and displays the result, both in the surface syntax and as trees.
+/// Reduction fails after `limit` interactions, if given.
+pub fn run(
+  path: impl AsRef<Path>,
+  net: &str,
+  limit: Option<usize>,
+  resolver: &dyn Resolver,
+) -> Result<String, Failure> {
+  let Checked { program, files, .. } = check_file_with(path, &CheckOptions::default(), resolver)?;
+  let mut runtime = Runtime::new(&program);
+  let Some(id) = runtime.net(net) else {
+    return Err(Failure::new(err!("no net named `{net}`"), files));
+  };
+  let result = runtime.instantiate(id).and_then(|mut net| {
+    let interactions = runtime.reduce(&mut net, limit)?;
+    Ok(format!("{}\n\n{}\n\n({interactions} interactions)", runtime.show_net(&net, id), runtime.show_trees(&net, id)))
+  });
+  result.map_err(|errors| Failure::new(errors, files))
+}
+
+/// Checks the program at `path`, then renders the net named `name`, or the
+/// rule for the components `A` and `B` if `name` is `A-B`, as a Graphviz
+/// graph. If `lifetimes` is set, the ends of each wire are labelled with the
+/// lifetimes of their ports.
+pub fn dot(path: impl AsRef<Path>, name: &str, lifetimes: bool, resolver: &dyn Resolver) -> Result<String, Failure> {
+  let Checked { program, files, .. } = check_file_with(path, &CheckOptions::default(), resolver)?;
+  program.dot(name, lifetimes).ok_or_else(|| Failure::new(err!("no net or rule named `{name}`"), files))
+}
+
+/// Checks the program at `path`, even if it has check errors, and renders the
+/// lifetime orders of each agent, rule, and net as a Graphviz graph, with any
+/// cycle highlighted. Returns the name of each definition with its graph.
+pub fn dot_orders(
+  path: impl AsRef<Path>,
+  options: &CheckOptions,
+  resolver: &dyn Resolver,
+) -> Result<Vec<(String, String)>, Failure> {
+  let (mut program, _) = load_parsed(path, resolver)?;
+  program.check(options, &mut ErrorGroup::default());
+  Ok(program.dot_orders())
+}
+
+/// Checks the program at `path` and applies every suggested fix to the files
+/// it was loaded from, writing them with `resolver`, and returns the number of
+/// fixes applied. Fixes that overlap an earlier fix in the same file or are in
+/// the standard library are skipped. A program that cannot be loaded or has
+/// syntax errors is not fixed.
+pub fn fix(path: impl AsRef<Path>, options: &CheckOptions, resolver: &dyn Resolver) -> Result<usize, Failure> {
+  let (mut program, files) = load_parsed(path, resolver)?;
+  let Err(errors) = program.check(options, &mut ErrorGroup::default()).report("check errors:") else {
+    return Ok(0);
+  };
+  let mut fixes = errors.fixes();
+  fixes.sort_by_key(|&(span, _)| (span.file, std::cmp::Reverse(span.start)));
+  fixes.dedup_by_key(|(span, _)| *span);
+  let mut applied = 0;
+  for file_fixes in fixes.chunk_by(|(a, _), (b, _)| a.file == b.file) {
+    let file = &files[file_fixes[0].0.file];
+    let Some(canonical) = &file.canonical else { continue };
+    let mut contents = file.contents.clone();
+    let mut end = contents.len();
+    for (span, replacement) in file_fixes {
+      if span.end <= end {
+        contents.replace_range(span.start..span.end, replacement);
+        end = span.start;
+        applied += 1;
+      }
+    }
+    if let Err(e) = resolver.write(canonical, &contents) {
+      let error = err!("cannot write `{}`: {e}", file.name);
+      return Err(Failure::new(error, files));
+    }
+  }
+  Ok(applied)
+}
+
+/// Formats the file at `path` in the canonical layout, keeping its comments,
+/// reading and writing it with `resolver`, and returns whether it was already
+/// formatted. If `check` is set, the file is left unchanged.
+pub fn fmt(path: impl AsRef<Path>, check: bool, resolver: &dyn Resolver) -> Result<bool, Failure> {
+  let path = path.as_ref();
+  let name = path.display();
+  let read = || {
+    let canonical = resolver.canonicalize(path)?;
+    let contents = resolver.read(path, &canonical)?;
+    let contents = String::from_utf8(contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    io::Result::Ok((canonical, contents))
+  };
+  let (canonical, contents) = match read() {
+    Ok(read) => read,
+    Err(e) => return Err(Failure::new(err!("cannot read `{name}`: {e}"), Files::default())),
+  };
+  let formatted = match format::format(&contents) {
+    Ok(formatted) => formatted,
+    Err(errors) => {
+      let mut files = Files::default();
+      let file = files.add(name.to_string(), Some(canonical), contents.clone());
+      let errors = load::syntax_errors(file, &contents, errors).report("syntax errors:").unwrap_err();
+      return Err(Failure::new(errors, files));
+    }
+  };
+  if formatted == contents {
+    return Ok(true);
+  }
+  if !check {
+    if let Err(e) = resolver.write(&canonical, &formatted) {
+      return Err(Failure::new(err!("cannot write `{name}`: {e}"), Files::default()));
+    }
+  }
+  Ok(false)
 }
@@ -1,17 +1,55 @@
 mod check_satisfiable;
+mod dot;
 mod populate_bounds;
 
-use crate::{new_index, order::Order, scope::Scope};
+use crate::{
+  new_index,
+  order::{Order, Relation},
+  scope::Scope,
+  span::Span,
+  util::DisplayFn,
+  vars::Site,
+};
 use std::{
   fmt::{Debug, Display},
   ops::{Add, BitXor, Index, IndexMut, Not},
 };
 
+/// The lifetimes of a definition, with the order of its external lifetimes,
+/// which it may assume, and of its internal lifetimes, which it must ensure.
 #[derive(Debug, Clone, Default)]
 pub struct LifetimeCtx {
   pub lifetimes: Scope<Lifetime, LifetimeInfo>,
   pub ex_order: Order<Lifetime>,
   pub in_order: Order<Lifetime>,
+  /// Where this context was written, if it was written in the source.
+  pub header: Option<Header>,
+}
+
+/// The reason for a relation between two lifetimes.
+#[derive(Debug, Clone)]
+pub enum Origin {
+  /// The relation is part of the contract being checked.
+  Declared,
+  /// The relation is part of the contract inferred for the net being checked.
+  Inferred,
+  /// The relation is part of the contract of a node, e.g. `Dup#0`.
+  Contract(String),
+  /// The variable connects the two ports.
+  Wire(String, Site, Site),
+  /// The principal ports of the nodes a rule matches are connected.
+  PrincipalPair(String, String),
+  /// The auxiliary port of an agent has the dual type of its principal port.
+  Auxiliary(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+  /// The span of the `[...]`, or an empty span where it would be inserted.
+  pub span: Span,
+  /// The number of lifetimes declared in the header; any later lifetimes in
+  /// the context were imported while checking.
+  pub len: usize,
 }
 
 new_index!(pub Lifetime "lifetime");
@@ -19,8 +57,12 @@ new_index!(pub Lifetime "lifetime");
 #[derive(Debug, Clone)]
 pub struct LifetimeInfo {
   pub side: Side,
-  pub max: Option<Lifetime>,
-  pub min: Option<Lifetime>,
+  /// The lifetimes of the other side that bound this lifetime from above,
+  /// each with the relation of this lifetime to it (`<=` or `<`).
+  pub max: Vec<(Lifetime, Relation)>,
+  /// The lifetimes of the other side that bound this lifetime from below,
+  /// each with the relation of this lifetime to it (`>=` or `>`).
+  pub min: Vec<(Lifetime, Relation)>,
 }
 
 impl LifetimeCtx {
@@ -28,23 +70,100 @@ impl LifetimeCtx {
     |lt| self.lifetimes.name(lt)
   }
 
-  pub fn import(&mut self, from: &LifetimeCtx, invert: bool, prefix: impl Display) -> Lifetime {
+  pub fn span_lt(&self) -> impl Fn(Lifetime) -> Option<Span> + '_ {
+    |lt| self.lifetimes.span(lt)
+  }
+
+  pub(crate) fn import(
+    &mut self,
+    from: &LifetimeCtx,
+    invert: bool,
+    name: impl Fn(Lifetime) -> String,
+    span: impl Fn(Lifetime) -> Span,
+    origin: Origin,
+  ) -> Lifetime {
     let base = self.lifetimes.len();
-    for (_, name, info) in from.lifetimes.iter() {
+    for (lt, _, info) in from.lifetimes.iter() {
       self.lifetimes.push(
-        format!("'{prefix}{}", &name[1..]),
+        name(lt),
+        Some(span(lt)),
         Some(LifetimeInfo {
           side: info.side ^ invert,
-          min: info.min.map(|lt| base + lt),
-          max: info.max.map(|lt| base + lt),
+          min: info.min.iter().map(|&(lt, rel)| (base + lt, rel)).collect(),
+          max: info.max.iter().map(|&(lt, rel)| (base + lt, rel)).collect(),
         }),
       );
     }
     let (known, needs) = if invert { (&from.in_order, &from.ex_order) } else { (&from.ex_order, &from.in_order) };
-    self.ex_order.import(known, |lt| base + lt);
-    self.in_order.import(needs, |lt| base + lt);
+    self.ex_order.import_because(known, |lt| base + lt, &origin);
+    self.in_order.import_because(needs, |lt| base + lt, &origin);
     base
   }
+
+  /// Explains why `order` implies `a rel b`, with a line for each relation
+  /// along the way whose origin is known.
+  pub fn explain<'a>(
+    &'a self,
+    order: &'a Order<Lifetime>,
+  ) -> impl Fn(Lifetime, Lifetime, Relation) -> Vec<String> + 'a {
+    move |a, b, rel| {
+      let Some(path) = order.find_path(a, b, rel) else { return vec![] };
+      let steps = path.windows(2).filter_map(|step| {
+        let (x, y) = (step[0], step[1]);
+        let rel = order.els[x].rels[&y].lte_component()?;
+        let origin = order.origin(x, y, rel)?;
+        Some(format!("{} {rel:?} {} {origin}", self.lifetimes.name(x), self.lifetimes.name(y)))
+      });
+      steps.collect()
+    }
+  }
+
+  /// Displays the lifetimes declared in this context in the syntax of a
+  /// definition header, e.g. `['a < 'b | 'c]`; displays nothing if there are
+  /// no lifetimes.
+  pub fn show_decls(&self) -> impl Display + '_ {
+    DisplayFn(move |f| {
+      let declared = self.header.map_or(self.lifetimes.len().0, |header| header.len);
+      let mut mentioned = vec![false; self.lifetimes.len().0];
+      let [external, internal] = [Side::External, Side::Internal].map(|side| {
+        let show_decl = |lt: Lifetime| {
+          let flipped = self.lifetimes.get(lt).is_ok_and(|info| info.side != side);
+          format!("{}{}", self.lifetimes.name(lt), if flipped { "?" } else { "" })
+        };
+        let mut decls = vec![];
+        for (a, b, rel) in self[side].iter_forward().filter(|&(a, b, _)| a.0 < declared && b.0 < declared) {
+          decls.push(format!("{} {rel:?} {}", show_decl(a), show_decl(b)));
+          mentioned[a.0] = true;
+          mentioned[b.0] = true;
+        }
+        for (lt, _, info) in self.lifetimes.iter() {
+          if lt.0 < declared && !mentioned[lt.0] && info.side == side {
+            decls.push(show_decl(lt));
+          }
+        }
+        decls.join(", ")
+      });
+      match (&*external, &*internal) {
+        ("", "") => Ok(()),
+        (_, "") => write!(f, "[{external}]"),
+        ("", _) => write!(f, "[| {internal}]"),
+        _ => write!(f, "[{external} | {internal}]"),
+      }
+    })
+  }
+}
+
+impl Display for Origin {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Origin::Declared => write!(f, "by the contract"),
+      Origin::Inferred => write!(f, "by the inferred contract"),
+      Origin::Contract(node) => write!(f, "by the contract of {node}"),
+      Origin::Wire(var, a, b) => write!(f, "because `{var}` connects {a} to {b}"),
+      Origin::PrincipalPair(a, b) => write!(f, "because the principal ports of {a} and {b} are connected"),
+      Origin::Auxiliary(i) => write!(f, "because port {i} has the dual type of the principal port"),
+    }
+  }
 }
 
 impl Debug for Lifetime {
@@ -1,16 +1,22 @@
-use std::{borrow::Cow, fmt::Display};
+use std::fmt::Display;
 
 use super::{Lifetime, LifetimeCtx, Side};
-use crate::{display, error::Error, order::Order};
+use crate::{
+  display,
+  error::Error,
+  order::{Order, Relation},
+};
 
 impl LifetimeCtx {
-  pub fn check_contract_satisfiable(&mut self) -> Result<(), Error> {
+  pub(crate) fn check_contract_satisfiable(&mut self) -> Result<(), Error> {
     for side in [Side::External, Side::Internal] {
-      self[side].check_coherent(self.show_lt()).report(format_args!("impossible {side} constraints:"))?;
+      self[side]
+        .check_coherent(self.show_lt(), self.span_lt(), self.explain(&self[side]))
+        .report(format_args!("impossible {side} constraints:"))?;
     }
 
     for side in [Side::External, Side::Internal] {
-      self.populate_bounds(side)?;
+      self.populate_bounds(side);
     }
 
     for side in [Side::External, Side::Internal] {
@@ -28,7 +34,7 @@ impl LifetimeCtx {
     Ok(())
   }
 
-  pub fn check_satisfiable(
+  pub(crate) fn check_satisfiable(
     &self,
     side: Option<Side>,
     knows: &Order<Lifetime>,
@@ -38,7 +44,7 @@ impl LifetimeCtx {
     let cycle_message = &display!("validity requires incoherent lifetime constraints:");
     let diff_message = &display!("validity requires constraints not guaranteed:");
 
-    needs.check_coherent(self.show_lt()).report(cycle_message)?;
+    needs.check_coherent(self.show_lt(), self.span_lt(), self.explain(needs)).report(cycle_message)?;
     self._check_satisfiable(side, knows, needs, cycle_message, diff_message)?;
 
     Ok(())
@@ -52,25 +58,92 @@ impl LifetimeCtx {
     cycle_message: impl Display,
     diff_message: impl Display,
   ) -> Result<(), Error> {
-    needs.check_coherent(self.show_lt()).report(&cycle_message)?;
+    needs.check_coherent(self.show_lt(), self.span_lt(), self.explain(needs)).report(&cycle_message)?;
 
-    let mut new_knows = Cow::Borrowed(knows);
+    let mut choices = vec![];
     let mut problems = Order::default();
-    for (a, b, rel_ab) in needs.omit(&|lt| Some(self.lifetimes[lt].side) == side).difference(&knows) {
-      match (self.lifetimes[a].max, self.lifetimes[b].min) {
-        (Some(x), Some(y)) => new_knows.to_mut().relate(x, y, rel_ab),
-        (Some(x), None) => new_knows.to_mut().relate(x, b, rel_ab),
-        (None, Some(y)) => new_knows.to_mut().relate(a, y, rel_ab),
-        (None, None) => problems.relate(a, b, rel_ab),
+    for (a, b, rel_ab) in needs.omit(&|lt| Some(self.lifetimes[lt].side) == side).difference(knows) {
+      let (maxes, mins) = (&self.lifetimes[a].max, &self.lifetimes[b].min);
+      if maxes.is_empty() && mins.is_empty() {
+        problems.relate(a, b, rel_ab);
+        continue;
       }
+      // `a` is below every lifetime in `xs` and `b` is above every lifetime in
+      // `ys`, so `a rel_ab b` holds if any one `x` is related to any one `y`; a
+      // strict bound makes up for the strictness of `rel_ab`
+      let xs = if maxes.is_empty() { &[(a, Relation::LE)][..] } else { maxes };
+      let ys = if mins.is_empty() { &[(b, Relation::GE)][..] } else { mins };
+      let pairs = xs.iter().flat_map(|&(x, rel_ax)| {
+        ys.iter().map(move |&(y, rel_by)| {
+          let strict = !rel_ax.allows_equal() || !rel_by.allows_equal();
+          (x, y, if strict { rel_ab.or_equal() } else { rel_ab })
+        })
+      });
+      let reason = format!("so that {} {rel_ab:?} {}", self.lifetimes.name(a), self.lifetimes.name(b));
+      choices.push((reason, pairs.collect::<Vec<_>>()));
     }
 
-    if matches!(new_knows, Cow::Owned(_)) {
-      new_knows.check_coherent(self.show_lt()).report(&cycle_message)?;
+    if !choices.is_empty() {
+      // if no choice of pairs is coherent, the first pair of each choice that
+      // is not already known is related, to explain the incoherence
+      let new_knows = choose_pairs(knows, &choices).unwrap_or_else(|| {
+        let mut new_knows = knows.clone();
+        for (reason, pairs) in &choices {
+          if !pairs.iter().any(|&(x, y, rel)| new_knows.implies(x, y, rel)) {
+            let coherent = pairs.iter().find(|&&(x, y, rel)| {
+              let mut knows = new_knows.clone();
+              knows.relate(x, y, rel);
+              knows.find_cycles().is_empty()
+            });
+            let &(x, y, rel) = coherent.unwrap_or(&pairs[0]);
+            new_knows.relate_because(x, y, rel, reason);
+          }
+        }
+        new_knows
+      });
+      new_knows.check_coherent(self.show_lt(), self.span_lt(), self.explain(&new_knows)).report(&cycle_message)?;
     }
 
-    problems.verify_empty(self.show_lt()).report(diff_message)?;
+    problems.verify_empty(self.show_lt(), self.span_lt(), self.explain(needs)).report(diff_message).map_err(|err| {
+      // the missing constraints can be added to the contract if they only
+      // involve lifetimes declared in it
+      let Some(header) = self.header else { return err };
+      if problems.iter().any(|(a, b, _)| a.0 >= header.len || b.0 >= header.len) {
+        return err;
+      }
+      let mut fixed = self.clone();
+      let fixed_side = side.map_or(Side::External, |side| !side);
+      fixed[fixed_side].extend(problems.iter_forward());
+      if !fixed[fixed_side].find_cycles().is_empty() {
+        return err;
+      }
+      err.fix(header.span, fixed.show_decls())
+    })?;
 
     Ok(())
   }
 }
+
+// the pairs of bounds, any one of which satisfies a constraint, along with the
+// constraint as the reason for relating them
+type Choice = (String, Vec<(Lifetime, Lifetime, Relation)>);
+
+// relates one pair from each of `choices` in `knows`, such that the result is
+// coherent, if possible
+fn choose_pairs(knows: &Order<Lifetime>, choices: &[Choice]) -> Option<Order<Lifetime>> {
+  let Some(((reason, pairs), rest)) = choices.split_first() else {
+    return Some(knows.clone());
+  };
+  if pairs.iter().any(|&(x, y, rel)| knows.implies(x, y, rel)) {
+    return choose_pairs(knows, rest);
+  }
+  pairs.iter().find_map(|&(x, y, rel)| {
+    let mut knows = knows.clone();
+    knows.relate_because(x, y, rel, reason);
+    if knows.find_cycles().is_empty() {
+      choose_pairs(&knows, rest)
+    } else {
+      None
+    }
+  })
+}
@@ -0,0 +1,58 @@
+use std::{collections::HashSet, fmt::Write};
+
+use super::{Lifetime, LifetimeCtx, Side};
+use crate::order::Order;
+
+impl LifetimeCtx {
+  /// Renders the external and internal orders of this context as a Graphviz
+  /// graph named `name`.
+  ///
+  /// External lifetimes are drawn as ellipses and internal ones as boxes.
+  /// Relations are drawn from the lesser lifetime to the greater, solid if
+  /// strict (`<`) and dashed otherwise (`<=`), in black for the external order
+  /// and blue for the internal one; each is annotated with its origin, if
+  /// known. The lifetimes and relations along any cycle in either order are
+  /// drawn in red.
+  pub fn show_orders(&self, name: &str) -> String {
+    let mut output = format!("digraph \"{name}\" {{\n");
+    let cycles = [Side::External, Side::Internal].map(|side| {
+      let mut edges = HashSet::new();
+      for cycle in self[side].find_cycles() {
+        edges.extend(cycle.windows(2).map(|step| (step[0], step[1])));
+      }
+      edges
+    });
+    let in_cycle = |lt| cycles.iter().flatten().any(|&(a, b)| a == lt || b == lt);
+    for i in 0..self.lifetimes.len().0 {
+      let lt = Lifetime(i);
+      let shape = match self.lifetimes.get(lt).map(|info| info.side) {
+        Ok(Side::External) => "ellipse",
+        Ok(Side::Internal) => "box",
+        Err(_) => "plaintext",
+      };
+      let color = if in_cycle(lt) { ", color=red" } else { "" };
+      writeln!(output, "  l{i} [label=\"{}\", shape={shape}{color}]", self.lifetimes.name(lt)).unwrap();
+    }
+    for (side, cycle_edges) in [Side::External, Side::Internal].into_iter().zip(&cycles) {
+      write_order(&mut output, &self[side], side, cycle_edges);
+    }
+    output.push_str("}\n");
+    output
+  }
+}
+
+fn write_order(output: &mut String, order: &Order<Lifetime>, side: Side, cycle_edges: &HashSet<(Lifetime, Lifetime)>) {
+  for (a, b, rel) in order.iter_forward() {
+    let style = if rel.allows_equal() { "dashed" } else { "solid" };
+    let color = match side {
+      _ if cycle_edges.contains(&(a, b)) => "red",
+      Side::External => "black",
+      Side::Internal => "blue",
+    };
+    write!(output, "  l{} -> l{} [style={style}, color={color}", a.0, b.0).unwrap();
+    if let Some(origin) = order.origin(a, b, rel) {
+      write!(output, ", tooltip=\"{}\"", origin.replace('"', "\\\"")).unwrap();
+    }
+    output.push_str("]\n");
+  }
+}
@@ -2,7 +2,7 @@ use super::{Lifetime, LifetimeCtx, Side};
 use crate::order::{Element, Relation, Transistor, TransistorConfig};
 
 impl LifetimeCtx {
-  pub fn populate_bounds(&mut self, side: Side) -> Result<(), String> {
+  pub(crate) fn populate_bounds(&mut self, side: Side) {
     let bounds = Transistor::new(
       &self[side],
       TransistorConfig {
@@ -13,49 +13,20 @@ impl LifetimeCtx {
     )
     .finish_where(|a| self.lifetimes[a].side == side);
 
-    for (a, name, info) in self.lifetimes.iter_mut() {
-      if info.side != side {
-        continue;
-      }
+    let lts = self.lifetimes.iter().filter(|(_, _, info)| info.side == side).map(|(a, ..)| a).collect::<Vec<_>>();
+    for a in lts {
       let Some(el) = bounds.els.get(a) else {
         continue;
       };
-      info.min = Self::get_bound(name, el, side, Relation::gte_component, "lower")?;
-      info.max = Self::get_bound(name, el, side, Relation::lte_component, "upper")?;
+      let info = &mut self.lifetimes[a];
+      info.min = Self::get_bounds(el, Relation::gte_component);
+      info.max = Self::get_bounds(el, Relation::lte_component);
     }
-
-    Ok(())
   }
 
-  fn get_bound(
-    lt: &str,
-    el: &Element<Lifetime>,
-    side: Side,
-    component: impl Fn(Relation) -> Option<Relation>,
-    bound_type: &str,
-  ) -> Result<Option<Lifetime>, String> {
-    let mut bounds = el.rels.iter().filter_map(|(&b, &r)| Some((b, component(r)?)));
-    Ok(if let Some((min, rel)) = bounds.next() {
-      if bounds.next().is_some() {
-        Err(format!(
-          "{side} lifetime `{lt}` has multiple {other_side} {bound_type} bounds
-  rewrite the contract so there is only one
-  (this is a temporary limitation of the checker)",
-          other_side = !side,
-        ))?;
-        todo!()
-      }
-      if !rel.allows_equal() {
-        Err(format!(
-          "{side} lifetime `{lt}`'s {other_side} {bound_type} bound is related with `<`, not `<=`
-  rewrite the contract so that it uses `<=`
-  (this is a temporary limitation of the checker)",
-          other_side = !side,
-        ))?;
-      }
-      Some(min)
-    } else {
-      None
-    })
+  fn get_bounds(el: &Element<Lifetime>, component: impl Fn(Relation) -> Option<Relation>) -> Vec<(Lifetime, Relation)> {
+    let mut bounds = el.rels.iter().filter_map(|(&b, &r)| Some((b, component(r)?))).collect::<Vec<_>>();
+    bounds.sort_by_key(|&(b, _)| b);
+    bounds
   }
 }
@@ -0,0 +1,266 @@
+use std::{
+  collections::HashMap,
+  io,
+  path::{Path, PathBuf},
+};
+
+use typed_arena::Arena;
+
+use crate::{
+  display, err,
+  error::{Error, ErrorGroup},
+  lower::Lowerer,
+  program::Program,
+  resolve::Resolver,
+  span::{FileId, Files, Span},
+  syntax::{Item, Parser, SourceFile, SyntaxError},
+};
+
+pub fn load(
+  initial_path: impl AsRef<Path>,
+  files: &mut Files,
+  resolver: &dyn Resolver,
+) -> Result<(Program, ErrorGroup), Error> {
+  Loader::run(files, resolver, |loader| {
+    loader.load(initial_path.as_ref().to_owned(), None);
+  })
+}
+
+pub fn load_source(
+  name: &str,
+  source: String,
+  files: &mut Files,
+  resolver: &dyn Resolver,
+) -> Result<(Program, ErrorGroup), Error> {
+  Loader::run(files, resolver, |loader| {
+    let path = PathBuf::from(name);
+    let canonical =
+      resolver.canonicalize(&path).or_else(|_| std::path::absolute(&path)).unwrap_or_else(|_| path.clone());
+    loader.load_contents(path, canonical, false, source.into_bytes(), None);
+  })
+}
+
+/// Locates each of the syntax errors found in `input`, the contents of `file`,
+/// at the character where it was found.
+pub fn syntax_errors(file: FileId, input: &str, errors: Vec<SyntaxError>) -> ErrorGroup {
+  let mut group = ErrorGroup::default();
+  for e in errors {
+    let end = input[e.offset..].chars().next().map_or(e.offset, |c| e.offset + c.len_utf8());
+    group.push(err!("{}", e.message).at(Span::new(file, e.offset, end)));
+  }
+  group
+}
+
+const STD: &[(&str, &str)] = &[
+  ("bool", include_str!("../std/bool.inlt")),
+  ("list", include_str!("../std/list.inlt")),
+  ("nat", include_str!("../std/nat.inlt")),
+];
+
+struct Loader<'a, 'i> {
+  files: &'a mut Files,
+  resolver: &'a dyn Resolver,
+  file_contents: &'i Arena<String>,
+  parser: Parser<'i>,
+  parsed: Vec<Parsed<'i>>,
+  loaded: HashMap<PathBuf, usize>,
+  chain: Vec<(PathBuf, String, Option<Span>)>,
+  errors: ErrorGroup,
+}
+
+struct Parsed<'i> {
+  file: FileId,
+  input: &'i str,
+  syntax: SourceFile<'i>,
+  includes: Vec<Option<usize>>,
+  errors: ErrorGroup,
+  chain: Vec<(String, Option<Span>)>,
+}
+
+impl<'a, 'i> Loader<'a, 'i> {
+  fn run(
+    files: &'a mut Files,
+    resolver: &'a dyn Resolver,
+    f: impl FnOnce(&mut Loader<'a, '_>),
+  ) -> Result<(Program, ErrorGroup), Error> {
+    let file_contents = Arena::<String>::new();
+    let mut loader = Loader {
+      files,
+      resolver,
+      file_contents: &file_contents,
+      parser: Parser::default(),
+      parsed: Vec::new(),
+      loaded: HashMap::new(),
+      chain: Vec::new(),
+      errors: ErrorGroup::default(),
+    };
+    f(&mut loader);
+    let Loader { files, mut parsed, mut errors, .. } = loader;
+
+    // modules are referred to by name, so two files may not share one
+    let mut named = HashMap::new();
+    for parsed in &parsed {
+      let name = &files[parsed.file].name;
+      let module = module_name(name);
+      let Some(first) = named.get(&module) else {
+        named.insert(module, parsed);
+        continue;
+      };
+      let first_name = &files[first.file].name;
+      let mut err = err!("`{first_name}` and `{name}` are both modules named `{module}`");
+      if let Some((_, Some(span))) = first.chain.last() {
+        err = err.label(*span, format_args!("`{first_name}` is included here"));
+      }
+      let (included_at, chain) = parsed.chain.split_last().unwrap();
+      errors.push(in_chain(err.at(included_at.1), chain));
+    }
+
+    let mut syntax_errors = ErrorGroup::default();
+    if !errors.is_empty() {
+      for parsed in &mut parsed {
+        let name = &files[parsed.file].name;
+        if let Err(err) = parsed.errors.report(display!("in `{name}`:")) {
+          syntax_errors.push(in_chain(err, &parsed.chain));
+        }
+      }
+      errors.push(std::mem::take(&mut syntax_errors));
+      errors.report("load errors:")?;
+    }
+
+    // every module is declared before any is lowered, so that names can refer
+    // to items in files included later
+    let mut lowerer = Lowerer::default();
+    let modules = parsed
+      .iter()
+      .map(|parsed| lowerer.declare_module(module_name(&files[parsed.file].name), parsed.file, &parsed.syntax))
+      .collect::<Vec<_>>();
+    for (parsed, &module) in parsed.iter_mut().zip(&modules) {
+      let includes = parsed.includes.iter().map(|&index| index.map(|index| modules[index])).collect::<Vec<_>>();
+      let mut file_errors = std::mem::take(&mut parsed.errors);
+      file_errors.push(lowerer.lower_file(module, parsed.input, &parsed.syntax, &includes));
+      let name = &files[parsed.file].name;
+      if let Err(err) = file_errors.report(display!("in `{name}`:")) {
+        syntax_errors.push(in_chain(err, &parsed.chain));
+      }
+    }
+    Ok((lowerer.finish(), syntax_errors))
+  }
+
+  fn load(&mut self, path: PathBuf, included_at: Option<Span>) -> Option<usize> {
+    match self.read(&path) {
+      Ok((canonical, contents)) => self.load_contents(path, canonical, true, contents, included_at),
+      Err(e) => self.error(err!("cannot read `{}`: {e}", path.display()), included_at),
+    }
+  }
+
+  fn read(&self, path: &Path) -> io::Result<(PathBuf, Vec<u8>)> {
+    let canonical = self.resolver.canonicalize(path)?;
+    let contents = self.resolver.read(path, &canonical)?;
+    Ok((canonical, contents))
+  }
+
+  fn include(&mut self, including: &Path, relative: &str, span: Span) -> Option<usize> {
+    let mut first_err = None;
+    for path in self.resolver.include_paths(including, relative) {
+      match self.read(&path) {
+        Ok((canonical, contents)) => return self.load_contents(path, canonical, true, contents, Some(span)),
+        Err(e) => {
+          first_err.get_or_insert((path, e));
+        }
+      }
+    }
+
+    // the standard library is only used if no file was found for the include,
+    // so that a file under a directory named `std` is not shadowed by it
+    if let Some(module) = relative.strip_prefix("std/") {
+      let module = module.strip_suffix(".inlt").unwrap_or(module);
+      let Some((_, source)) = STD.iter().find(|(name, _)| *name == module) else {
+        let modules = STD.iter().map(|(name, _)| format!("`std/{name}`")).collect::<Vec<_>>();
+        let err = err!("no module `{relative}` in the standard library, which has {}", modules.join(", "));
+        return self.error(err, Some(span));
+      };
+      let path = PathBuf::from(format!("std/{module}.inlt"));
+      let canonical = PathBuf::from(format!("<std>/{module}.inlt"));
+      return self.load_contents(path, canonical, false, source.as_bytes().to_vec(), Some(span));
+    }
+
+    let err = match first_err {
+      Some((path, e)) => err!("cannot read `{}`: {e}", path.display()),
+      None => err!("cannot resolve `{relative}`"),
+    };
+    self.error(err, Some(span))
+  }
+
+  fn load_contents(
+    &mut self,
+    path: PathBuf,
+    canonical: PathBuf,
+    resolved: bool,
+    contents: Vec<u8>,
+    included_at: Option<Span>,
+  ) -> Option<usize> {
+    let name = path.display().to_string();
+
+    if let Some(start) = self.chain.iter().position(|(path, ..)| *path == canonical) {
+      let mut cycle = self.chain[start..].iter().map(|(_, name, _)| format!("`{name}`")).collect::<Vec<_>>();
+      cycle.push(format!("`{name}`"));
+      return self.error(err!("include cycle: {}", cycle.join(" -> ")), included_at);
+    }
+
+    if let Some(&index) = self.loaded.get(&canonical) {
+      return Some(index);
+    }
+
+    let Ok(contents) = String::from_utf8(contents) else {
+      return self.error(err!("`{name}` is not valid UTF-8"), included_at);
+    };
+
+    let input = self.file_contents.alloc(contents);
+    let file = self.files.add(name.clone(), resolved.then(|| canonical.clone()), input.clone());
+    let (syntax, parse_errors) = self.parser.parse_file(input);
+    let errors = syntax_errors(file, input, parse_errors);
+    let includes = syntax
+      .items
+      .iter()
+      .filter_map(|item| match item {
+        Item::Include(item) => Some((item.path.text, Span::new(file, item.path.start, item.path.end()))),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    let chain = self.chain.iter().map(|(_, name, span)| (name.clone(), *span)).chain([(name.clone(), included_at)]);
+    let chain = chain.collect();
+    let index = self.parsed.len();
+    self.parsed.push(Parsed { file, input, syntax, includes: vec![], errors, chain });
+    self.loaded.insert(canonical.clone(), index);
+
+    self.chain.push((canonical, name, included_at));
+    for (relative, span) in includes {
+      let included = self.include(&path, relative, span);
+      self.parsed[index].includes.push(included);
+    }
+    self.chain.pop();
+    Some(index)
+  }
+
+  fn error(&mut self, err: Error, included_at: Option<Span>) -> Option<usize> {
+    let chain = self.chain.iter().map(|(_, name, span)| (name.clone(), *span)).collect::<Vec<_>>();
+    self.errors.push(in_chain(err.at(included_at), &chain));
+    None
+  }
+}
+
+/// The name of the module of the file named `name`, such as `nat` for
+/// `std/nat.inlt`.
+fn module_name(name: &str) -> String {
+  let path = Path::new(name);
+  path.file_stem().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
+
+fn in_chain(mut err: Error, chain: &[(String, Option<Span>)]) -> Error {
+  for (name, span) in chain.iter().rev() {
+    if let Some(span) = *span {
+      err = err.label(span, format_args!("`{name}` is included here"));
+    }
+  }
+  err
+}
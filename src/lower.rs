@@ -0,0 +1,550 @@
+use std::collections::HashMap;
+
+use crate::{
+  err,
+  error::{Error, ErrorGroup},
+  globals::{Component, ComponentInfo, Module, ModuleInfo, Polarity, PortLabel, Type, TypeInfo},
+  index_vec::IndexVec,
+  lifetimes::{Header, Lifetime, LifetimeCtx, LifetimeInfo, Origin, Side},
+  order::Relation,
+  program::{AgentDef, NetDef, Node, Program, Ref, RuleDef, TypeDef},
+  scope::ScopeBuilder,
+  span::{FileId, Span},
+  syntax::{self, Item, LtCtx, LtPart, NodeLike, SourceFile, Token},
+  vars::{Var, VarCtx, VarInfo},
+};
+
+#[derive(Default)]
+pub struct Lowerer<'i> {
+  input: &'i str,
+  file: FileId,
+  program: Program,
+  items: IndexVec<Module, Items<'i>>,
+  module: Module,
+  includes: Vec<Module>,
+  uses: HashMap<&'i str, (Module, &'i str)>,
+  types: ScopeBuilder<'i, Type, TypeInfo>,
+  components: ScopeBuilder<'i, Component, ComponentInfo>,
+  lifetimes: ScopeBuilder<'i, Lifetime, LifetimeInfo>,
+  vars: ScopeBuilder<'i, Var, VarInfo>,
+  port_names: HashMap<Component, Vec<Option<&'i str>>>,
+  lt_refs: Vec<(Span, Lifetime)>,
+  errors: ErrorGroup,
+}
+
+#[derive(Default)]
+struct Items<'i> {
+  types: HashMap<&'i str, (Type, bool)>,
+  components: HashMap<&'i str, (Component, bool)>,
+}
+
+impl<'i> Lowerer<'i> {
+  pub fn declare_module(&mut self, name: String, file: FileId, syntax: &SourceFile<'i>) -> Module {
+    let module = self.program.globals.modules.push(ModuleInfo { name, file });
+    let mut items = Items::default();
+    // a file that marks nothing `pub` exports everything, as every file did
+    // before files were modules
+    let export_all = syntax.items.iter().all(|item| match item {
+      Item::Type(item) => item.vis.is_none(),
+      Item::Agent(item) => item.vis.is_none(),
+      Item::Net(item) => item.vis.is_none(),
+      Item::Include(_) | Item::Use(_) | Item::Rule(_) => true,
+    });
+    for item in &syntax.items {
+      let (name, public, port_names) = match item {
+        Item::Type(item) => {
+          let name = item.ty.name.text;
+          if !items.types.contains_key(name) {
+            let ty = self.new_type(name, module);
+            items.types.insert(name, (ty, export_all || item.vis.is_some()));
+          }
+          continue;
+        }
+        Item::Agent(item) => {
+          let port_names = item.node.ports.iter().map(|(port, _)| port.name.as_ref().map(|(name, _)| name.text));
+          (item.node.name.text, item.vis.is_some(), port_names.collect())
+        }
+        Item::Net(item) => {
+          let port_names = item.node.ports.iter().map(|(port, _)| Some(port.var.text));
+          (item.node.name.text, item.vis.is_some(), port_names.collect())
+        }
+        Item::Include(_) | Item::Use(_) | Item::Rule(_) => continue,
+      };
+      if !items.components.contains_key(name) {
+        let component = self.new_component(name, module);
+        items.components.insert(name, (component, export_all || public));
+        self.port_names.insert(component, port_names);
+      }
+    }
+    self.items.push(items);
+    module
+  }
+
+  pub fn lower_file(
+    &mut self,
+    module: Module,
+    input: &'i str,
+    syntax: &SourceFile<'i>,
+    includes: &[Option<Module>],
+  ) -> ErrorGroup {
+    self.module = module;
+    self.file = self.program.globals.modules[module].file;
+    self.input = input;
+    self.types.lookup.clear();
+    self.components.lookup.clear();
+    self.includes.clear();
+    self.uses.clear();
+    // the items of included modules may be used before the `include`
+    let mut includes = includes.iter();
+    for item in &syntax.items {
+      match item {
+        Item::Include(_) => {
+          if let Some(&Some(included)) = includes.next() {
+            self.include(included);
+          }
+        }
+        Item::Use(item) => self.lower_use(item),
+        _ => {}
+      }
+    }
+    for item in &syntax.items {
+      match item {
+        Item::Include(_) | Item::Use(_) => {}
+        Item::Type(item) => self.lower_type_def(item),
+        Item::Agent(item) => self.lower_agent_def(item),
+        Item::Rule(item) => self.lower_rule_def(item),
+        Item::Net(item) => self.lower_net_def(item),
+      }
+    }
+    std::mem::take(&mut self.errors)
+  }
+
+  pub fn finish(mut self) -> Program {
+    self.program.globals.types = self.types.finish();
+    self.program.globals.components = self.components.finish();
+    self.program
+  }
+
+  fn include(&mut self, module: Module) {
+    if !self.includes.contains(&module) {
+      self.includes.push(module);
+    }
+  }
+
+  fn lower_use(&mut self, item: &syntax::Use<'i>) {
+    let module = item.module.text;
+    let Some(&from) = self.includes.iter().find(|&&x| self.program.globals.modules[x].name == module) else {
+      self.errors.push(err!("undefined module `{module}`").at(self.span(&item.module)));
+      return;
+    };
+    for (name, _) in &item.names {
+      let span = self.span(&name.name);
+      let name_text = name.name.text;
+      let items = &self.items[from];
+      let found = [items.types.get(name_text).map(|x| x.1), items.components.get(name_text).map(|x| x.1)];
+      if found.iter().all(Option::is_none) {
+        self.errors.push(err!("no type or component named `{name_text}` in module `{module}`").at(span));
+      } else if !found.contains(&Some(true)) {
+        self.errors.push(err!("`{name_text}` is private to module `{module}`").at(span));
+      }
+      let alias = name.alias.as_ref().map_or(name_text, |(_, alias)| alias.text);
+      self.uses.insert(alias, (from, name_text));
+    }
+  }
+
+  fn lower_type_def(&mut self, item: &syntax::TypeDef<'i>) {
+    let id = self.items[self.module].types[item.ty.name.text].0;
+    self.program.refs.push((self.span(&item.ty.name), Ref::Type(id)));
+    let id = if item.ty.bang.is_some() { !id } else { id };
+    let span = self.span_to(item.ty.bang.as_ref().unwrap_or(&item.ty.name), &item.ty.name);
+    let polarity = if item.polarity.text == "+" { Polarity::Pos } else { Polarity::Neg };
+    self.program.types.push(TypeDef { span, id, polarity });
+  }
+
+  fn lower_agent_def(&mut self, item: &syntax::AgentDef<'i>) {
+    let mut lt_ctx = self.lower_lt_ctx(item.lt_ctx.as_ref(), &item.keyword);
+    let id = self.items[self.module].components[item.node.name.text].0;
+    let (span, ports) = self.lower_node_like(&item.node, id, |slf, port| slf.lower_port_label(&port.label));
+    let mut seen = HashMap::new();
+    let mut port_names = vec![];
+    for (port, _) in &item.node.ports {
+      let Some((name, _)) = &port.name else {
+        port_names.push(None);
+        continue;
+      };
+      let span = self.span(name);
+      if let Some(&prev) = seen.get(name.text) {
+        self.errors.push(err!("duplicate port name `{}`", name.text).at(span).label(prev, "previously used here"));
+      }
+      seen.entry(name.text).or_insert(span);
+      port_names.push(Some(name.text.to_owned()));
+    }
+    self.finish_lt_ctx(&mut lt_ctx);
+    self.program.agents.push(AgentDef { span, id, lt_ctx, ports, port_names });
+  }
+
+  fn lower_rule_def(&mut self, item: &syntax::RuleDef<'i>) {
+    self.vars.ensure_empty();
+    let a = self.lower_redex_node(&item.a);
+    let b = self.lower_redex_node(&item.b);
+    let result = self.lower_body(&item.body);
+    let var_ctx = VarCtx { vars: self.vars.finish() };
+    let span = a.span.to(b.span);
+    self.program.rules.push(RuleDef { span, var_ctx, lt_ctx: LifetimeCtx::default(), a, b, result });
+  }
+
+  fn lower_net_def(&mut self, item: &syntax::NetDef<'i>) {
+    // the contract is inferred if the lifetime context is omitted or is `[_]`
+    let (lt_ctx, before) = match &item.lt_ctx {
+      Some(LtCtx { parts, close, .. }) if matches!(parts[..], [LtPart::Hole(_)]) => (None, close),
+      lt_ctx => (lt_ctx.as_ref(), &item.keyword),
+    };
+    let infer = lt_ctx.is_none();
+    let mut lt_ctx = self.lower_lt_ctx(lt_ctx, before);
+    self.vars.ensure_empty();
+    let id = self.items[self.module].components[item.node.name.text].0;
+    let (span, free_ports) = self.lower_node_like(&item.node, id, |slf, port| {
+      let (var, _) = slf.lower_var(&port.var);
+      (var, slf.lower_port_label(&port.label))
+    });
+    if infer {
+      for &(_, PortLabel(_, lt, span)) in &free_ports {
+        self.lifetimes.scope.or_define(lt, span, || LifetimeInfo { side: Side::External, min: vec![], max: vec![] });
+      }
+    }
+    let nodes = self.lower_body(&item.body);
+    let var_ctx = VarCtx { vars: self.vars.finish() };
+    self.finish_lt_ctx(&mut lt_ctx);
+    self.program.nets.push(NetDef { span, id, infer, lt_ctx, var_ctx, free_ports, nodes });
+  }
+
+  fn lower_body(&mut self, body: &syntax::Body<'i>) -> Vec<Node> {
+    let mut nodes = vec![];
+    for node in &body.nodes {
+      self.lower_node(node, None, &mut nodes);
+    }
+    nodes
+  }
+
+  fn lower_redex_node(&mut self, node: &syntax::Node<'i>) -> Node {
+    let mut nodes = vec![];
+    self.lower_node(node, None, &mut nodes);
+    if let Some(nested) = nodes.get(1) {
+      self.errors.push(err!("nodes cannot be nested in the redex of a rule").at(nested.span));
+    }
+    nodes.swap_remove(0)
+  }
+
+  fn lower_node(&mut self, node: &syntax::Node<'i>, parent: Option<(usize, usize)>, nodes: &mut Vec<Node>) {
+    let index = nodes.len();
+    let component = self.lower_component(&node.name);
+    let wire = parent.map(|_| {
+      let name = format!("{}#{index}", node.name.text);
+      self.vars.scope.push(name, Some(self.span_to(&node.name, &node.close)), Some(VarInfo::default()))
+    });
+    let principal = self.principal_arg(node, component);
+    let mut arg_index = 0;
+    let (span, ports) = self.lower_node_like(node, component, |slf, arg| {
+      let principal = principal == Some(arg_index);
+      arg_index += 1;
+      match &arg.port {
+        syntax::Port::Var(token) => match wire {
+          Some(wire) if principal => {
+            if token.text != "_" {
+              slf.errors.push(err!("the principal port of a nested node must be `_`").at(slf.span(token)));
+            }
+            (wire, slf.span(token))
+          }
+          _ => slf.lower_var(token),
+        },
+        syntax::Port::Node(inner) => {
+          let span = slf.span_to(&inner.name, &inner.close);
+          if principal {
+            slf.errors.push(err!("nodes can only be nested in auxiliary ports").at(span));
+          }
+          // replaced by the wire to the nested node once it is lowered
+          (Var::default(), span)
+        }
+      }
+    });
+    let (ports, port_spans) = ports.into_iter().unzip();
+    nodes.push(Node { span, component, ports, port_spans });
+    if let (Some((parent, port)), Some(wire)) = (parent, wire) {
+      nodes[parent].ports[port] = wire;
+    }
+    for (port, (arg, _)) in node.ports.iter().enumerate() {
+      if let syntax::Port::Node(inner) = &arg.port {
+        self.lower_node(inner, Some((index, port)), nodes);
+      }
+    }
+    self.order_ports(node, &mut nodes[index]);
+  }
+
+  fn principal_arg(&self, node: &syntax::Node<'i>, component: Component) -> Option<usize> {
+    match node.ports.first() {
+      Some((syntax::Arg { name: None, .. }, _)) => Some(0),
+      _ => {
+        let principal = *self.port_names.get(&component)?.first()?;
+        node.ports.iter().position(|(arg, _)| arg.name.as_ref().map(|(name, _)| name.text) == principal)
+      }
+    }
+  }
+
+  fn order_ports(&mut self, node: &syntax::Node<'i>, lowered: &mut Node) {
+    if node.ports.iter().all(|(arg, _)| arg.name.is_none()) {
+      return;
+    }
+    let names = self.port_names.get(&lowered.component).map_or(&[][..], Vec::as_slice);
+    if names.len() != node.ports.len() {
+      return;
+    }
+    let mut errors = ErrorGroup::default();
+    let mut order = vec![None; names.len()];
+    let mut by_name = false;
+    for (i, (arg, _)) in node.ports.iter().enumerate() {
+      let Some((name, _)) = &arg.name else {
+        if by_name {
+          errors.push(err!("ports given by position must come before those given by name").at(lowered.port_spans[i]));
+        } else {
+          order[i] = Some(i);
+        }
+        continue;
+      };
+      by_name = true;
+      let span = self.span(name);
+      match names.iter().position(|&x| x == Some(name.text)) {
+        None => {
+          errors.push(err!("`{}` has no port named `{}`", node.name.text, name.text).at(span));
+        }
+        Some(j) => match order[j] {
+          Some(prev) => {
+            let err = err!("port `{}` of `{}` is given more than once", name.text, node.name.text);
+            errors.push(err.at(span).label(lowered.port_spans[prev], "previously given here"));
+          }
+          None => order[j] = Some(i),
+        },
+      }
+    }
+    if errors.is_empty() {
+      let order = order.into_iter().map(Option::unwrap);
+      (lowered.ports, lowered.port_spans) = order.map(|i| (lowered.ports[i], lowered.port_spans[i])).unzip();
+    } else {
+      // the ports cannot be matched up, so their uses are not checked
+      for &var in &lowered.ports {
+        self.vars.scope.poison(var);
+      }
+      self.errors.push(errors);
+    }
+  }
+
+  fn lower_port_label(&mut self, label: &syntax::PortLabel<'i>) -> PortLabel {
+    let ty = self.lower_type(&label.ty);
+    let lt = self.lower_lt(&label.lt);
+    PortLabel(ty, lt, self.span_to(label.ty.bang.as_ref().unwrap_or(&label.ty.name), &label.lt))
+  }
+
+  fn lower_type(&mut self, ty: &syntax::TypeRef<'i>) -> Type {
+    let name = ty.name.text;
+    let id = match self.types.lookup.get(name) {
+      Some(&id) => id,
+      None => {
+        let id = match self.resolve(&ty.name, |items| &items.types) {
+          Ok(Some(id)) => id,
+          Ok(None) => self.new_type(name, self.module),
+          Err(err) => {
+            self.errors.push(err);
+            let id = self.new_type(name, self.module);
+            self.types.scope.poison(id);
+            self.types.scope.poison(!id);
+            id
+          }
+        };
+        self.types.lookup.insert(name, id);
+        id
+      }
+    };
+    self.program.refs.push((self.span(&ty.name), Ref::Type(id)));
+    if ty.bang.is_some() {
+      !id
+    } else {
+      id
+    }
+  }
+
+  fn lower_component(&mut self, token: &Token<'i>) -> Component {
+    let name = token.text;
+    if let Some(&id) = self.components.lookup.get(name) {
+      return id;
+    }
+    let id = match self.resolve(token, |items| &items.components) {
+      Ok(Some(id)) => id,
+      Ok(None) => self.new_component(name, self.module),
+      Err(err) => {
+        self.errors.push(err);
+        let id = self.new_component(name, self.module);
+        self.components.scope.poison(id);
+        id
+      }
+    };
+    self.components.lookup.insert(name, id);
+    id
+  }
+
+  fn resolve<K: Copy>(
+    &self,
+    token: &Token<'i>,
+    items: impl for<'a> Fn(&'a Items<'i>) -> &'a HashMap<&'i str, (K, bool)>,
+  ) -> Result<Option<K>, Error> {
+    let span = self.span(token);
+    let modules = &self.program.globals.modules;
+    if let Some((module, name)) = token.text.rsplit_once("::") {
+      let Some(&from) = self.includes.iter().find(|&&x| modules[x].name == module) else {
+        return Err(err!("undefined module `{module}`").at(span));
+      };
+      return match items(&self.items[from]).get(name) {
+        Some(&(id, true)) => Ok(Some(id)),
+        Some(_) => Err(err!("`{name}` is private to module `{module}`").at(span)),
+        None => Ok(None),
+      };
+    }
+
+    let name = token.text;
+    if let Some(&(id, _)) = items(&self.items[self.module]).get(name) {
+      return Ok(Some(id));
+    }
+    if let Some(&(from, original)) = self.uses.get(name) {
+      if let Some(&(id, true)) = items(&self.items[from]).get(original) {
+        return Ok(Some(id));
+      }
+    }
+    let found = self.includes.iter().filter_map(|&x| Some((x, *items(&self.items[x]).get(name)?))).collect::<Vec<_>>();
+    let public = found.iter().filter(|(_, (_, public))| *public).collect::<Vec<_>>();
+    match (&public[..], found.first()) {
+      ([], None) => Ok(None),
+      ([], Some(&(from, _))) => Err(err!("`{name}` is private to module `{}`", modules[from].name).at(span)),
+      ([(_, (id, _))], _) => Ok(Some(*id)),
+      (public, _) => {
+        let modules = public.iter().map(|(x, _)| format!("`{}`", modules[*x].name)).collect::<Vec<_>>();
+        Err(err!("ambiguous name `{name}`, which is defined in modules {}", modules.join(" and ")).at(span))
+      }
+    }
+  }
+
+  fn new_type(&mut self, name: &str, module: Module) -> Type {
+    self.types.scope.push(format!("!{name}"), None, None);
+    self.program.globals.type_modules.push(module);
+    self.program.globals.type_modules.push(module);
+    self.types.scope.push(name.to_owned(), None, None)
+  }
+
+  fn new_component(&mut self, name: &str, module: Module) -> Component {
+    self.program.globals.component_modules.push(module);
+    self.components.scope.push(name.to_owned(), None, None)
+  }
+
+  fn lower_node_like<S, T>(
+    &mut self,
+    node: &NodeLike<'i, S>,
+    component: Component,
+    mut lower_elem: impl FnMut(&mut Self, &S) -> T,
+  ) -> (Span, Vec<T>) {
+    self.program.refs.push((self.span(&node.name), Ref::Component(component)));
+    let elems = node.ports.iter().map(|(port, _)| lower_elem(self, port)).collect();
+    (self.span_to(&node.name, &node.close), elems)
+  }
+
+  fn lower_lt_ctx(&mut self, ctx: Option<&LtCtx<'i>>, before: &Token<'i>) -> LifetimeCtx {
+    self.lifetimes.ensure_empty();
+    self.lt_refs.clear();
+    let mut lt_ctx = LifetimeCtx::default();
+    let Some(ctx) = ctx else {
+      lt_ctx.header = Some(Header { span: Span::new(self.file, before.end(), before.end()), len: 0 });
+      return lt_ctx;
+    };
+    let mut side = Side::External;
+    let mut prev = None;
+    let mut rel = None;
+    for part in &ctx.parts {
+      match part {
+        LtPart::Hole(_) => {}
+        LtPart::Op(op) => {
+          rel = match op.text {
+            "<" | "<=" => Some(Relation::LE),
+            ">" | ">=" => Some(Relation::GE),
+            "|" => {
+              side = Side::Internal;
+              prev = None;
+              None
+            }
+            _ => None,
+          };
+          if let Some(rel) = &mut rel {
+            if !op.text.ends_with('=') {
+              *rel = rel.not_equal();
+            }
+          }
+        }
+        LtPart::Decl(decl) => {
+          let next = self.lower_lt_decl(decl, side);
+          if let (Some(prev), Some(rel)) = (prev, rel.take()) {
+            match side {
+              Side::External => lt_ctx.ex_order.relate_because(prev, next, rel, Origin::Declared),
+              Side::Internal => lt_ctx.in_order.relate_because(prev, next, rel, Origin::Declared),
+            }
+          }
+          prev = Some(next);
+        }
+      }
+    }
+    lt_ctx.header = Some(Header { span: self.span_to(&ctx.open, &ctx.close), len: 0 });
+    lt_ctx
+  }
+
+  fn finish_lt_ctx(&mut self, lt_ctx: &mut LifetimeCtx) {
+    lt_ctx.lifetimes = self.lifetimes.finish();
+    if let Some(header) = &mut lt_ctx.header {
+      header.len = lt_ctx.lifetimes.len().0;
+    }
+    for (span, lt) in self.lt_refs.drain(..) {
+      let decl = lt_ctx.lifetimes.get(lt).ok().map(|info| (lt_ctx.lifetimes.span(lt).unwrap(), info.side));
+      self.program.refs.push((span, Ref::Lifetime(decl)));
+    }
+  }
+
+  fn lower_var(&mut self, token: &Token<'i>) -> (Var, Span) {
+    let span = self.span(token);
+    let var = *self
+      .vars
+      .lookup
+      .entry(token.text)
+      .or_insert_with(|| self.vars.scope.push(token.text.to_owned(), Some(span), Some(VarInfo::default())));
+    (var, span)
+  }
+
+  fn lower_lt_decl(&mut self, decl: &syntax::LtDecl<'i>, side: Side) -> Lifetime {
+    let lt = self.lower_lt(&decl.lt);
+    let side = side ^ decl.question.is_some();
+    let span = self.span_to(&decl.lt, decl.question.as_ref().unwrap_or(&decl.lt));
+    let info = self.lifetimes.scope.or_define(lt, span, || LifetimeInfo { side, min: vec![], max: vec![] });
+    if info.side != side {
+      self.errors.push(
+        err!("inconsistent external/internal modifiers on lifetime `{}`", self.lifetimes.scope.name(lt)).at(span),
+      );
+    }
+    lt
+  }
+
+  fn lower_lt(&mut self, token: &Token<'i>) -> Lifetime {
+    let lt = self.lifetimes.get(token.text);
+    self.lt_refs.push((self.span(token), lt));
+    lt
+  }
+
+  fn span(&self, token: &Token) -> Span {
+    Span::new(self.file, token.start, token.end())
+  }
+
+  fn span_to(&self, start: &Token, end: &Token) -> Span {
+    Span::new(self.file, start.start, end.end())
+  }
+}
@@ -1,22 +1,220 @@
-use std::{env, process::ExitCode};
+use std::{
+  env, fs,
+  path::{Path, PathBuf},
+  process::ExitCode,
+};
 
-use inet_lifetimes::check;
+use inet_lifetimes::{
+  check, check_json, dot, dot_orders, fix, fmt,
+  program::{CheckOptions, Level},
+  resolve::{FileSystem, Resolver, SearchPaths},
+  run,
+};
 
 fn main() -> ExitCode {
-  let mut any = false;
+  let Some((args, dirs)) = search_dirs(env::args().skip(1)) else {
+    println!("expected a directory after `-I`");
+    return ExitCode::FAILURE;
+  };
+  let resolver = SearchPaths::from_env(FileSystem, dirs);
+  match args.first().map(|x| &**x) {
+    Some("run") => run_net(&args[1..], &resolver),
+    Some("fmt") => fmt_paths(&args[1..], &resolver),
+    Some("dot") => dot_graph(&args[1..], &resolver),
+    _ => check_paths(&args, &resolver),
+  }
+}
+
+fn search_dirs(mut args: impl Iterator<Item = String>) -> Option<(Vec<String>, Vec<PathBuf>)> {
+  let mut rest = vec![];
+  let mut dirs = vec![];
+  while let Some(arg) = args.next() {
+    match arg.strip_prefix("-I") {
+      Some("") => dirs.push(PathBuf::from(args.next()?)),
+      Some(dir) => dirs.push(PathBuf::from(dir)),
+      None => rest.push(arg),
+    }
+  }
+  Some((rest, dirs))
+}
+
+fn check_paths(args: &[String], resolver: &dyn Resolver) -> ExitCode {
+  let mut options = CheckOptions::default();
+  let mut fix_paths = false;
+  let mut dump_orders = None;
+  let mut json = false;
+  let mut paths = vec![];
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    let level = match &**arg {
+      "--fix" => {
+        fix_paths = true;
+        continue;
+      }
+      "--dump-orders" => {
+        match args.next() {
+          Some(dir) => dump_orders = Some(Path::new(dir)),
+          None => {
+            println!("expected a directory after `--dump-orders`");
+            return ExitCode::FAILURE;
+          }
+        }
+        continue;
+      }
+      "--format" => {
+        match args.next().map(|x| &**x) {
+          Some("text") => json = false,
+          Some("json") => json = true,
+          Some(format) => {
+            println!("unknown format `{format}`");
+            return ExitCode::FAILURE;
+          }
+          None => {
+            println!("expected `text` or `json` after `--format`");
+            return ExitCode::FAILURE;
+          }
+        }
+        continue;
+      }
+      "-A" | "--allow" => Level::Allow,
+      "-W" | "--warn" => Level::Warn,
+      "-D" | "--deny" => Level::Deny,
+      _ => {
+        paths.push(arg);
+        continue;
+      }
+    };
+    match args.next().map(|x| &**x) {
+      Some("missing-rules") => options.missing_rules = level,
+      Some("inferred-contracts") => options.inferred_contracts = level,
+      Some(lint) => {
+        println!("unknown lint `{lint}`");
+        return ExitCode::FAILURE;
+      }
+      None => {
+        println!("expected a lint name after `{arg}`");
+        return ExitCode::FAILURE;
+      }
+    }
+  }
   let mut code = ExitCode::SUCCESS;
-  for path in env::args().skip(1) {
-    any = true;
-    if let Err(e) = check(&path) {
-      println!("{path}:\n\n{}\n\n", e);
-      code = ExitCode::FAILURE;
-    } else {
-      println!("{path}: ok")
+  for path in &paths {
+    if fix_paths {
+      match fix(path, &options, resolver) {
+        Ok(0) => {}
+        Ok(n) => eprintln!("{path}: applied {n} fix{}", if n == 1 { "" } else { "es" }),
+        Err(e) => eprintln!("{path}: {e}"),
+      }
+    }
+    if let Some(dir) = dump_orders {
+      if let Err(e) = write_orders(path, &options, dir, resolver) {
+        eprintln!("{path}: {e}");
+      }
+    }
+    if json {
+      // only diagnostics are printed, one per line, so that the output can be
+      // consumed as JSON lines
+      match check_json(path, &options, resolver) {
+        Err(e) => {
+          println!("{}", e.show_json_lines());
+          code = ExitCode::FAILURE;
+        }
+        Ok(Some(warnings)) => println!("{warnings}"),
+        Ok(None) => {}
+      }
+      continue;
+    }
+    match check(path, &options, resolver) {
+      Err(e) => {
+        println!("{path}:\n\n{}\n\n", e);
+        code = ExitCode::FAILURE;
+      }
+      Ok(Some(warnings)) => println!("{path}: ok, with warnings:\n\n{warnings}\n\n"),
+      Ok(None) => println!("{path}: ok"),
     }
   }
-  if !any {
+  if paths.is_empty() {
     println!("supply a path");
     code = ExitCode::FAILURE;
   }
   code
 }
+
+fn write_orders(path: &str, options: &CheckOptions, dir: &Path, resolver: &dyn Resolver) -> Result<(), String> {
+  fs::create_dir_all(dir).map_err(|e| format!("cannot create `{}`: {e}", dir.display()))?;
+  for (name, graph) in dot_orders(path, options, resolver).map_err(|e| e.to_string())? {
+    let file = dir.join(format!("{}.dot", name.replace('/', "_")));
+    fs::write(&file, graph).map_err(|e| format!("cannot write `{}`: {e}", file.display()))?;
+  }
+  Ok(())
+}
+
+fn fmt_paths(args: &[String], resolver: &dyn Resolver) -> ExitCode {
+  let check = args.iter().any(|x| x == "--check");
+  let paths = args.iter().filter(|x| *x != "--check").collect::<Vec<_>>();
+  let mut code = ExitCode::SUCCESS;
+  for path in &paths {
+    match fmt(path, check, resolver) {
+      Ok(true) => {}
+      Ok(false) if check => {
+        println!("{path}: not formatted");
+        code = ExitCode::FAILURE;
+      }
+      Ok(false) => println!("{path}: formatted"),
+      Err(e) => {
+        println!("{path}:\n\n{e}\n\n");
+        code = ExitCode::FAILURE;
+      }
+    }
+  }
+  if paths.is_empty() {
+    println!("usage: inet-lifetimes fmt [--check] <path>...");
+    code = ExitCode::FAILURE;
+  }
+  code
+}
+
+fn run_net(args: &[String], resolver: &dyn Resolver) -> ExitCode {
+  let (limit, args) = match args {
+    [flag, limit, rest @ ..] if flag == "--max-interactions" => match limit.parse() {
+      Ok(limit) => (Some(limit), rest),
+      Err(_) => {
+        println!("expected a number of interactions after `--max-interactions`");
+        return ExitCode::FAILURE;
+      }
+    },
+    _ => (None, args),
+  };
+  let [path, net] = args else {
+    println!("usage: inet-lifetimes run [--max-interactions <n>] <path> <net>");
+    return ExitCode::FAILURE;
+  };
+  match run(path, net, limit, resolver) {
+    Ok(result) => {
+      println!("{result}");
+      ExitCode::SUCCESS
+    }
+    Err(e) => {
+      println!("{path}:\n\n{}\n\n", e);
+      ExitCode::FAILURE
+    }
+  }
+}
+
+fn dot_graph(args: &[String], resolver: &dyn Resolver) -> ExitCode {
+  let lifetimes = args.iter().any(|x| x == "--lifetimes");
+  let [path, name] = &args.iter().filter(|x| *x != "--lifetimes").collect::<Vec<_>>()[..] else {
+    println!("usage: inet-lifetimes dot [--lifetimes] <path> <net or rule>");
+    return ExitCode::FAILURE;
+  };
+  match dot(path, name, lifetimes, resolver) {
+    Ok(graph) => {
+      print!("{graph}");
+      ExitCode::SUCCESS
+    }
+    Err(e) => {
+      println!("{path}:\n\n{}\n\n", e);
+      ExitCode::FAILURE
+    }
+  }
+}
@@ -1,12 +1,14 @@
 mod find_cycles;
+mod find_path;
 mod relation;
 mod transistor;
 
 pub use relation::*;
-pub use transistor::*;
+pub(crate) use transistor::*;
 
 use std::{
   cell::Cell,
+  collections::HashMap,
   fmt::{Debug, Display},
 };
 
@@ -15,13 +17,17 @@ use crate::{
   error::{Error, ErrorGroup},
   globals::Polarity,
   index_vec::{Idx, IndexVec},
+  span::Span,
   util::DisplayFn,
 };
 use nohash_hasher::IntMap;
 
+/// Relations between elements, such as `'a < 'b`, which need not be
+/// transitively closed; [`Order::implies`] asks what follows from them.
 #[derive(Clone)]
 pub struct Order<I: Idx> {
   pub els: IndexVec<I, Element<I>>,
+  origins: HashMap<(I, I, Relation), String>,
 }
 
 #[derive(Clone)]
@@ -40,7 +46,7 @@ enum Flag {
 
 impl<I: Idx> Default for Order<I> {
   fn default() -> Self {
-    Self { els: Default::default() }
+    Self { els: Default::default(), origins: Default::default() }
   }
 }
 
@@ -85,23 +91,57 @@ impl<I: Idx> Order<I> {
     }
   }
 
-  pub fn relate_polarity(&mut self, a: I, b: I, rel: Relation, polarity: Polarity) {
-    self.relate(
+  /// Relates `a` and `b`, recording `origin` as the reason unless a reason is
+  /// already known.
+  pub fn relate_because(&mut self, a: I, b: I, rel: Relation, origin: impl Display) {
+    self.relate(a, b, rel);
+    // the reason is kept for each direction the relation goes in, so that it
+    // is not given for a relation added later in the other direction
+    for (x, y, rel) in [(a, b, rel), (b, a, rel.rev())] {
+      if let Some(rel) = rel.lte_component() {
+        self.origins.entry((x, y, rel)).or_insert_with(|| origin.to_string());
+      }
+    }
+  }
+
+  pub fn relate_polarity(&mut self, a: I, b: I, rel: Relation, polarity: Polarity, origin: impl Display) {
+    self.relate_because(
       a,
       b,
       match polarity {
         Polarity::Pos => rel,
         Polarity::Neg => rel.rev(),
       },
+      origin,
     )
   }
 
+  /// Imports the relations of `from`, along with their origins.
   pub fn import<J: Idx>(&mut self, from: &Order<J>, f: impl Fn(J) -> I) {
     for (a, b, rel) in from.iter() {
       if a < b {
         self.relate(f(a), f(b), rel);
       }
     }
+    for (&(a, b, rel), origin) in &from.origins {
+      self.origins.entry((f(a), f(b), rel)).or_insert_with(|| origin.clone());
+    }
+  }
+
+  /// Imports the relations of `from`, attributing them all to `origin`.
+  pub fn import_because<J: Idx>(&mut self, from: &Order<J>, f: impl Fn(J) -> I, origin: impl Display) {
+    let origin = origin.to_string();
+    for (a, b, rel) in from.iter() {
+      if a < b {
+        self.relate_because(f(a), f(b), rel, &origin);
+      }
+    }
+  }
+
+  /// Why `a rel b`, where `rel` is `<=` or `<`, if known.
+  pub fn origin(&self, a: I, b: I, rel: Relation) -> Option<&str> {
+    let strict = || self.origins.get(&(a, b, Relation::LT)).filter(|_| rel == Relation::LE);
+    self.origins.get(&(a, b, rel)).or_else(strict).map(|x| &**x)
   }
 
   pub fn iter(&self) -> impl Iterator<Item = (I, I, Relation)> + '_ {
@@ -118,31 +158,77 @@ impl<I: Idx> Order<I> {
     }
   }
 
-  pub fn verify_empty<D: Display>(&self, display_item: impl Fn(I) -> D) -> ErrorGroup {
+  /// Reports each relation in this order as an error, noting the lines of
+  /// `explain` for it.
+  pub fn verify_empty<D: Display>(
+    &self,
+    display_item: impl Fn(I) -> D,
+    span_item: impl Fn(I) -> Option<Span>,
+    explain: impl Fn(I, I, Relation) -> Vec<String>,
+  ) -> ErrorGroup {
     let mut errors = ErrorGroup::default();
     for (a, b, rel) in self.iter_forward() {
-      errors.push(err!("{} {rel:?} {}", display_item(a), display_item(b)));
+      let mut err = err!("{} {rel:?} {}", display_item(a), display_item(b));
+      err = Self::label_items(err, [a, b], &display_item, &span_item);
+      for line in explain(a, b, rel) {
+        err = err.note(line);
+      }
+      errors.push(err);
     }
     errors
   }
 
-  pub fn check_coherent<D: Display>(&self, display_item: impl Fn(I) -> D) -> ErrorGroup {
+  /// Reports each cycle in this order as an error, noting the lines of
+  /// `explain` for each relation along it.
+  pub fn check_coherent<D: Display>(
+    &self,
+    display_item: impl Fn(I) -> D,
+    span_item: impl Fn(I) -> Option<Span>,
+    explain: impl Fn(I, I, Relation) -> Vec<String>,
+  ) -> ErrorGroup {
     let mut errors = ErrorGroup::default();
     let cycles = self.find_cycles();
     for cycle in cycles {
-      errors.push(self.show_cycle(cycle, &display_item));
+      let mut err = self.show_cycle(&cycle, &display_item);
+      err = Self::label_items(err, cycle.iter().copied(), &display_item, &span_item);
+      for step in cycle.windows(2) {
+        let rel = self.els[step[0]].rels[&step[1]].lte_component().unwrap();
+        for line in explain(step[0], step[1], rel) {
+          err = err.note(line);
+        }
+      }
+      errors.push(err);
     }
     errors
   }
 
-  pub fn show_cycle<'a, D: Display>(&'a self, cycle: Vec<I>, display_item: impl Fn(I) -> D) -> Error
-  where
-    I: 'a,
-  {
+  fn label_items<D: Display>(
+    mut err: Error,
+    items: impl IntoIterator<Item = I>,
+    display_item: impl Fn(I) -> D,
+    span_item: impl Fn(I) -> Option<Span>,
+  ) -> Error {
+    let mut labels: Vec<(Span, Vec<String>)> = vec![];
+    for item in items {
+      let Some(span) = span_item(item) else { continue };
+      let name = format!("`{}`", display_item(item));
+      match labels.iter_mut().find(|(s, _)| *s == span) {
+        Some((_, names)) if names.contains(&name) => {}
+        Some((_, names)) => names.push(name),
+        None => labels.push((span, vec![name])),
+      }
+    }
+    for (span, names) in labels {
+      err = err.label(span, names.join(", "));
+    }
+    err
+  }
+
+  pub fn show_cycle<D: Display>(&self, cycle: &[I], display_item: impl Fn(I) -> D) -> Error {
     Error::from(
       DisplayFn(move |f| {
         let mut last = None;
-        for &b in &cycle {
+        for &b in cycle {
           if let Some(a) = last {
             write!(f, " {:?} ", self.els[a].rels[&b].lte_component().unwrap())?;
           }
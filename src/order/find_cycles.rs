@@ -10,8 +10,8 @@ impl<I: Idx> Order<I> {
     for a in self.els.keys() {
       finder.visit(a, 0);
     }
-    debug_assert!(finder.active_cycles_0.len() == 0);
-    debug_assert!(finder.active_cycles_1.len() == 0);
+    debug_assert!(finder.active_cycles_0.is_empty());
+    debug_assert!(finder.active_cycles_1.is_empty());
     finder.finished_cycles
   }
 }
@@ -134,7 +134,7 @@ mod tests {
       assert!(every_node_represented(&order, &cycles));
     }
 
-    fn every_node_represented(order: &Order<usize>, cycles: &Vec<Vec<usize>>) -> bool {
+    fn every_node_represented(order: &Order<usize>, cycles: &[Vec<usize>]) -> bool {
       order.els.keys().all(|a| cycles.iter().any(|cycle| cycle.contains(&a)))
     }
   }
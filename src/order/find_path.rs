@@ -0,0 +1,58 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::index_vec::Idx;
+
+use super::{Order, Relation};
+
+impl<I: Idx> Order<I> {
+  /// Finds a shortest chain of relations in this order that implies `a rel b`,
+  /// returning the elements along it, starting with `a` and ending with `b`.
+  pub fn find_path(&self, a: I, b: I, rel: Relation) -> Option<Vec<I>> {
+    let strict = !rel.allows_equal();
+    // each state is an element along with whether the chain reaching it
+    // includes a strict relation; `prev` maps each state to the one before it
+    let start = (a, false);
+    let mut prev = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some((x, x_strict)) = queue.pop_front() {
+      let Some(el) = self.els.get(x) else { continue };
+      for (&y, &rel_xy) in &el.rels {
+        let Some(rel_xy) = rel_xy.lte_component() else { continue };
+        let state = (y, x_strict || !rel_xy.allows_equal());
+        if state == start || prev.contains_key(&state) {
+          continue;
+        }
+        prev.insert(state, (x, x_strict));
+        if y == b && (state.1 || !strict) {
+          let mut path = vec![b];
+          let mut state = state;
+          while state != start {
+            state = prev[&state];
+            path.push(state.0);
+          }
+          path.reverse();
+          return Some(path);
+        }
+        queue.push_back(state);
+      }
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::order::{Order, Relation};
+
+  const LE: Relation = Relation::LE;
+  const LT: Relation = Relation::LT;
+
+  #[test]
+  fn test_find_path() {
+    let order = Order::from_iter([(0, 1, LE), (1, 2, LE), (0, 3, LT), (3, 2, LE)]);
+    assert_eq!(order.find_path(0, 2, LE).map(|path| path.len()), Some(3));
+    assert_eq!(order.find_path(0, 2, LT), Some(vec![0, 3, 2]));
+    assert_eq!(order.find_path(1, 2, LT), None);
+    assert_eq!(order.find_path(2, 0, LE), None);
+  }
+}
@@ -4,6 +4,7 @@ use std::{
   ops::{Add, BitAnd, BitAndAssign},
 };
 
+/// How one element relates to another: `<=`, `<`, `>=`, `>`, or `==`.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Relation(NonZeroU8);
 
@@ -38,11 +39,19 @@ impl Relation {
     Self::new(self.val() | ((self.val() & 0b1010) >> 1))
   }
 
+  /// This relation, but allowing equality, e.g. `<=` for `<`.
+  pub fn or_equal(self) -> Self {
+    Self::new(self.val() & 0b10_10)
+  }
+
   pub fn allows_equal(self) -> bool {
     (self.val() & 0b01_01) == 0
   }
 }
 
+// relations are bitsets of the orderings they rule out, so `&` is `|`, here
+// and in the impls below
+#[allow(clippy::suspicious_arithmetic_impl)]
 impl BitAnd for Relation {
   type Output = Relation;
 
@@ -51,12 +60,14 @@ impl BitAnd for Relation {
   }
 }
 
+#[allow(clippy::suspicious_op_assign_impl)]
 impl BitAndAssign for Relation {
   fn bitand_assign(&mut self, rhs: Self) {
     self.0 |= rhs.0;
   }
 }
 
+#[allow(clippy::suspicious_arithmetic_impl)]
 impl Add for Relation {
   type Output = Option<Relation>;
 
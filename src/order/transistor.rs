@@ -3,7 +3,7 @@ use crate::index_vec::Idx;
 use super::{Flag, Order, Relation};
 
 impl<I: Idx> Order<I> {
-  pub fn complete(&self) -> Transistor<I> {
+  pub fn complete(&self) -> Transistor<'_, I> {
     Transistor::new(
       self,
       TransistorConfig {
@@ -33,11 +33,18 @@ impl<I: Idx> Order<I> {
     for (a, el) in &mut output.els {
       if omit(a) {
         *el = Default::default();
+      } else {
+        el.rels.retain(|&b, _| !omit(b));
       }
     }
     output
   }
 
+  /// Whether `a rel b` follows from the relations in this order.
+  pub fn implies(&self, a: I, b: I, rel: Relation) -> bool {
+    !self.complete().has(a, b, rel)
+  }
+
   fn has(&self, a: I, b: I, rel: Relation) -> bool {
     self.els.get(a).and_then(|x| x.rels.get(&b)).is_some_and(|&has_rel| (has_rel & rel) == has_rel)
   }
@@ -1,11 +1,40 @@
 use crate::{
-  globals::{Component, GlobalCtx, Polarity, PortLabel, Type},
-  lifetimes::LifetimeCtx,
-  vars::{Var, VarCtx},
+  globals::{Component, ComponentInfo, GlobalCtx, Polarity, PortLabel, Type},
+  lifetimes::{Lifetime, LifetimeCtx, Side},
+  scope::Scope,
+  span::Span,
+  vars::{Var, VarCtx, VarInfo},
 };
 
 mod check;
 
+/// Configures the checks performed by [`Program::check`] that are not always
+/// errors.
+#[derive(Debug, Clone)]
+pub struct CheckOptions {
+  /// How to report pairs of agents that can interact but have no rule.
+  pub missing_rules: Level,
+  /// How to report the contracts inferred for nets that omit them. They are
+  /// not reported by default, as omitting a contract is not a mistake.
+  pub inferred_contracts: Level,
+}
+
+impl Default for CheckOptions {
+  fn default() -> Self {
+    CheckOptions { missing_rules: Level::Warn, inferred_contracts: Level::Allow }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Level {
+  Allow,
+  #[default]
+  Warn,
+  Deny,
+}
+
+/// The definitions of a program and the global context they are checked in,
+/// which names every type and component.
 #[derive(Debug, Clone, Default)]
 pub struct Program {
   pub globals: GlobalCtx,
@@ -13,24 +42,42 @@ pub struct Program {
   pub agents: Vec<AgentDef>,
   pub rules: Vec<RuleDef>,
   pub nets: Vec<NetDef>,
+  /// Every name of a type, component, or lifetime in the source, with what it
+  /// refers to.
+  pub refs: Vec<(Span, Ref)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Ref {
+  Type(Type),
+  Component(Component),
+  /// A lifetime, with the span and side of its declaration, if it has one.
+  Lifetime(Option<(Span, Side)>),
 }
 
 #[derive(Debug, Clone)]
 pub struct TypeDef {
+  pub span: Span,
   pub id: Type,
   pub polarity: Polarity,
 }
 
 #[derive(Debug, Clone)]
 pub struct AgentDef {
+  pub span: Span,
   pub id: Component,
   pub lt_ctx: LifetimeCtx,
   pub ports: Vec<PortLabel>,
+  pub port_names: Vec<Option<String>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct RuleDef {
+  pub span: Span,
   pub var_ctx: VarCtx,
+  /// The lifetimes of the matched nodes and the result, imported while
+  /// checking.
+  pub lt_ctx: LifetimeCtx,
   pub a: Node,
   pub b: Node,
   pub result: Vec<Node>,
@@ -38,7 +85,10 @@ pub struct RuleDef {
 
 #[derive(Debug, Clone)]
 pub struct NetDef {
+  pub span: Span,
   pub id: Component,
+  /// Whether the lifetime context was omitted, to be inferred from the body.
+  pub infer: bool,
   pub var_ctx: VarCtx,
   pub lt_ctx: LifetimeCtx,
   pub free_ports: Vec<(Var, PortLabel)>,
@@ -47,6 +97,56 @@ pub struct NetDef {
 
 #[derive(Debug, Clone)]
 pub struct Node {
+  pub span: Span,
   pub component: Component,
   pub ports: Vec<Var>,
+  pub port_spans: Vec<Span>,
+}
+
+impl Program {
+  /// The agent named `name`, which may be qualified by its module, as in
+  /// `nat::Succ`.
+  pub fn agent(&self, name: &str) -> Option<&AgentDef> {
+    self.agents.iter().find(|agent| self.globals.component_matches(agent.id, name))
+  }
+
+  /// The net named `name`, which may be qualified by its module.
+  pub fn net(&self, name: &str) -> Option<&NetDef> {
+    self.nets.iter().find(|net| self.globals.component_matches(net.id, name))
+  }
+
+  /// The rule for the components named `a` and `b`, in either order.
+  pub fn rule(&self, a: &str, b: &str) -> Option<&RuleDef> {
+    let matches = |x, y| self.globals.component_matches(x, a) && self.globals.component_matches(y, b);
+    self
+      .rules
+      .iter()
+      .find(|rule| matches(rule.a.component, rule.b.component) || matches(rule.b.component, rule.a.component))
+  }
+}
+
+impl Node {
+  /// Names each lifetime of the component's context imported for this node,
+  /// which is named `name`: after the wire at the only port labelled with it,
+  /// as in `'y@Dup#0`, or else after the lifetime itself, as in `'Dup#0.a`.
+  pub fn lt_name<'a>(
+    &'a self,
+    name: &'a str,
+    component: &'a ComponentInfo,
+    vars: &'a Scope<Var, VarInfo>,
+  ) -> impl Fn(Lifetime) -> String + 'a {
+    move |lt| {
+      let mut wires = component.ports.iter().zip(&self.ports).filter(|(label, _)| label.1 == lt);
+      match (wires.next(), wires.next()) {
+        (Some((_, &var)), None) if vars.name(var) != "_" => format!("'{}@{name}", vars.name(var)),
+        _ => format!("'{name}.{}", &component.lt_ctx.lifetimes.name(lt)[1..]),
+      }
+    }
+  }
+
+  /// Locates each lifetime of the component's `signature` in this node: at the
+  /// first port labelled with it, or else at the whole node.
+  pub fn lt_span<'a>(&'a self, signature: &'a [PortLabel]) -> impl Fn(Lifetime) -> Span + 'a {
+    |lt| signature.iter().zip(&self.port_spans).find(|(label, _)| label.1 == lt).map_or(self.span, |(_, &span)| span)
+  }
 }
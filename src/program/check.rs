@@ -2,13 +2,17 @@ use crate::{
   display, err,
   error::{Error, ErrorGroup},
   globals::{ComponentInfo, GlobalCtx, PortLabel, TypeInfo},
-  lifetimes::{LifetimeCtx, Side},
+  lifetimes::{Origin, Side},
   order::{Order, Relation},
-  program::{AgentDef, NetDef, Program, RuleDef, TypeDef},
+  program::{AgentDef, CheckOptions, Level, NetDef, Program, RuleDef, TypeDef},
+  vars::Site,
 };
+use std::collections::{HashMap, HashSet};
 
 impl Program {
-  pub fn check(&mut self) -> ErrorGroup {
+  /// Checks the program, returning any errors; diagnostics that `options`
+  /// demote to warnings are pushed to `warnings`.
+  pub fn check(&mut self, options: &CheckOptions, warnings: &mut ErrorGroup) -> ErrorGroup {
     let mut errors = ErrorGroup::default();
 
     for ty in &self.types {
@@ -19,7 +23,15 @@ impl Program {
       errors.push(agent.define(&mut self.globals));
     }
 
-    for net in &mut self.nets {
+    // nets with inferred contracts are defined last, so that their bodies may
+    // use any net whose contract is written out
+    for net in self.nets.iter_mut().filter(|net| !net.infer) {
+      errors.push(net.define(&mut self.globals));
+    }
+
+    for i in self.inference_order(&mut errors) {
+      let net = &mut self.nets[i];
+      net.infer_contract(&self.globals);
       errors.push(net.define(&mut self.globals));
     }
 
@@ -29,27 +41,135 @@ impl Program {
       }
     }
 
-    errors
-      .push(self.globals.type_order.check_coherent(|ty| self.globals.types.name(ty)).report("incoherent type order:"));
+    let types = &self.globals.types;
+    errors.push(
+      self
+        .globals
+        .type_order
+        .check_coherent(|ty| types.name(ty), |ty| types.span(ty), |_, _, _| vec![])
+        .report("incoherent type order:"),
+    );
+
+    errors.push(self.check_duplicate_rules());
 
     for rule in self.rules.iter_mut() {
-      errors.push(rule.check(&mut self.globals));
+      errors.push(rule.check(&self.globals));
     }
 
     for net in self.nets.iter_mut() {
       if !self.globals.components.poisoned(net.id) {
-        errors.push(net.check(&mut self.globals));
+        let ok = errors.push(net.check(&self.globals)).is_some();
+        if ok && net.infer && !net.free_ports.is_empty() {
+          let header = self.globals.show_net_header(net.id, |i| net.var_ctx.vars.name(net.free_ports[i].0));
+          let note = err!("inferred contract: {header}").at(net.span);
+          match options.inferred_contracts {
+            Level::Allow => {}
+            Level::Warn => warnings.push(note),
+            Level::Deny => errors.push(note),
+          }
+        }
       }
     }
 
+    match options.missing_rules {
+      Level::Allow => {}
+      Level::Warn => _ = warnings.push(self.check_coverage()),
+      Level::Deny => _ = errors.push(self.check_coverage()),
+    }
+
     errors
   }
+
+  fn inference_order(&mut self, errors: &mut ErrorGroup) -> Vec<usize> {
+    let inferred = self.nets.iter().enumerate().filter(|(_, net)| net.infer).map(|(i, net)| (net.id, i));
+    let inferred = inferred.collect::<HashMap<_, _>>();
+    let mut order = vec![];
+    let mut done = vec![false; self.nets.len()];
+    let mut recursive = HashSet::new();
+    for start in (0..self.nets.len()).filter(|&i| self.nets[i].infer) {
+      // each entry is a net being visited and the index of its next node
+      let mut stack = vec![(start, 0)];
+      while let Some((i, next)) = stack.last_mut() {
+        let (i, node) = (*i, *next);
+        if done[i] {
+          stack.pop();
+          continue;
+        }
+        *next += 1;
+        match self.nets[i].nodes.get(node).and_then(|node| inferred.get(&node.component)) {
+          Some(&j) => match stack.iter().position(|&(k, _)| k == j) {
+            Some(cycle) => recursive.extend(stack[cycle..].iter().map(|&(k, _)| k)),
+            None if done[j] => {}
+            None => stack.push((j, 0)),
+          },
+          None if node < self.nets[i].nodes.len() => {}
+          None => {
+            done[i] = true;
+            if !recursive.contains(&i) {
+              order.push(i);
+            }
+            stack.pop();
+          }
+        }
+      }
+    }
+    let mut recursive = recursive.into_iter().collect::<Vec<_>>();
+    recursive.sort();
+    for i in recursive {
+      let net = &self.nets[i];
+      let name = self.globals.components.name(net.id);
+      errors
+        .push(err!("cannot infer the contract of `{name}`, which uses itself; it must be written out").at(net.span));
+      self.globals.components.poison(net.id);
+    }
+    order
+  }
+
+  fn check_duplicate_rules(&self) -> ErrorGroup {
+    let mut errors = ErrorGroup::default();
+    let mut seen = HashMap::new();
+    for rule in &self.rules {
+      let (a, b) = (rule.a.component, rule.b.component);
+      if let Some(prev) = seen.insert((a.min(b), a.max(b)), rule.span) {
+        let components = &self.globals.components;
+        errors.push(
+          err!("duplicate rule for `{}` and `{}`", components.name(a), components.name(b))
+            .at(rule.span)
+            .label(prev, "previously defined here"),
+        );
+      }
+    }
+    errors
+  }
+
+  fn check_coverage(&self) -> Result<(), Error> {
+    let mut errors = ErrorGroup::default();
+    let globals = &self.globals;
+    let rules =
+      self.rules.iter().flat_map(|rule| [(rule.a.component, rule.b.component), (rule.b.component, rule.a.component)]);
+    let rules = rules.collect::<HashSet<_>>();
+    let agents = self.agents.iter().filter(|agent| !globals.components.poisoned(agent.id) && !agent.ports.is_empty());
+    let agents = agents.collect::<Vec<_>>();
+    for (i, a) in agents.iter().enumerate() {
+      for b in &agents[i + 1..] {
+        if a.ports[0].0 == !b.ports[0].0 && !rules.contains(&(a.id, b.id)) {
+          let (a_name, b_name) = (globals.components.name(a.id), globals.components.name(b.id));
+          errors.push(
+            err!("no rule for `{a_name}` and `{b_name}`")
+              .at(a.span)
+              .label(b.span, format_args!("`{b_name}` is defined here")),
+          );
+        }
+      }
+    }
+    errors.report("missing rules:")
+  }
 }
 
 impl TypeDef {
   fn define(&self, globals: &mut GlobalCtx) -> Result<(), Error> {
-    globals.types.try_define(self.id, || TypeInfo { polarity: self.polarity })?;
-    globals.types.try_define(!self.id, || TypeInfo { polarity: !self.polarity })?;
+    globals.types.try_define(self.id, self.span, || TypeInfo { polarity: self.polarity })?;
+    globals.types.try_define(!self.id, self.span, || TypeInfo { polarity: !self.polarity })?;
     Ok(())
   }
 }
@@ -59,34 +179,31 @@ impl AgentDef {
     let mut errors = ErrorGroup::default();
 
     for port in &self.ports {
-      errors.push(globals.types.get(port.0));
-      errors.push(self.lt_ctx.lifetimes.get(port.1));
+      errors.push(globals.types.get(port.0).map_err(|err| err.at(port.2)));
+      errors.push(self.lt_ctx.lifetimes.get(port.1).map_err(|err| err.at(port.2)));
     }
 
     errors.push(self.lt_ctx.check_contract_satisfiable());
 
-    errors.push(
-      globals
-        .components
-        .try_define(self.id, || ComponentInfo { lt_ctx: self.lt_ctx.clone(), ports: self.ports.clone() }),
-    );
+    errors.push(globals.components.try_define(self.id, self.span, || ComponentInfo {
+      lt_ctx: self.lt_ctx.clone(),
+      ports: self.ports.clone(),
+      port_names: self.port_names.clone(),
+    }));
 
     let name = globals.components.name(self.id);
     let ctx = display!("in agent `{name}`:");
 
-    errors.report(ctx).map_err(|err| {
-      globals.components.poison(self.id);
-      err
-    })
+    errors.report(ctx).inspect_err(|_| globals.components.poison(self.id))
   }
 
   fn check(&mut self, globals: &mut GlobalCtx) -> Result<(), Error> {
     let mut required = Order::default();
 
     let pri = self.ports[0];
-    for aux in &self.ports[1..] {
+    for (i, aux) in self.ports.iter().enumerate().skip(1) {
       if !aux.0 == pri.0 {
-        required.relate_polarity(aux.1, pri.1, Relation::LT, globals.types[pri.0].polarity);
+        required.relate_polarity(aux.1, pri.1, Relation::LT, globals.types[pri.0].polarity, Origin::Auxiliary(i));
       } else {
         globals.type_order.relate(!aux.0, pri.0, Relation::LT);
       }
@@ -111,14 +228,19 @@ impl RuleDef {
     let ctx = &display!("in rule `{a_name}-{b_name}`:");
 
     if self.a.ports[0] != self.b.ports[0] {
-      errors.push(err!("matched nodes are not connected by their principal ports"));
+      errors.push(
+        err!("matched nodes are not connected by their principal ports")
+          .at(self.span)
+          .label(self.a.port_spans[0], "this port")
+          .label(self.b.port_spans[0], "is not connected to this port"),
+      );
     }
 
-    let a = errors.push(globals.components.get(self.a.component));
-    let b = errors.push(globals.components.get(self.b.component));
+    let a = errors.push(globals.components.get(self.a.component).map_err(|err| err.at(self.a.span)));
+    let b = errors.push(globals.components.get(self.b.component).map_err(|err| err.at(self.b.span)));
 
     for node in &self.result {
-      errors.push(globals.components.get(node.component));
+      errors.push(globals.components.get(node.component).map_err(|err| err.at(node.span)));
     }
 
     errors.report(ctx)?;
@@ -126,26 +248,35 @@ impl RuleDef {
     let a = a.unwrap();
     let b = b.unwrap();
 
-    let mut lt_ctx = LifetimeCtx::default();
-    let a_base = lt_ctx.import(&a.lt_ctx, false, format_args!("{}.", a_name));
-    let b_base = lt_ctx.import(&b.lt_ctx, false, format_args!("{}.", b_name));
+    let lt_ctx = &mut self.lt_ctx;
+    let a_origin = Origin::Contract(a_name.to_owned());
+    let b_origin = Origin::Contract(b_name.to_owned());
+    let vars = &self.var_ctx.vars;
+    let (a_lt_name, b_lt_name) = (self.a.lt_name(a_name, a, vars), self.b.lt_name(b_name, b, vars));
+    let a_base = lt_ctx.import(&a.lt_ctx, false, a_lt_name, self.a.lt_span(&a.ports), a_origin);
+    let b_base = lt_ctx.import(&b.lt_ctx, false, b_lt_name, self.b.lt_span(&b.ports), b_origin);
     lt_ctx.ex_order.relate_polarity(
       a_base + a.ports[0].1,
       b_base + b.ports[0].1,
       Relation::LE,
       globals.types[a.ports[0].0].polarity,
+      Origin::PrincipalPair(a_name.to_owned(), b_name.to_owned()),
     );
 
-    for (lt_base, source_node) in [(a_base, &self.a), (b_base, &self.b)] {
+    for (lt_base, source_node, info, name) in [(a_base, &self.a, a, a_name), (b_base, &self.b, b, b_name)] {
       if let Some(pairs) = errors.push(self.var_ctx.check_node_arity(source_node, globals)) {
         for (i, (var, label)) in pairs.enumerate() {
-          self.var_ctx.vars[var].uses.push(PortLabel(if i != 0 { !label.0 } else { label.0 }, lt_base + label.1))
+          self.var_ctx.add_use(
+            var,
+            PortLabel(if i != 0 { !label.0 } else { label.0 }, lt_base + label.1, label.2),
+            Site::Node(name.to_owned(), i, info.port_names[i].clone()),
+          )
         }
       }
     }
 
-    self.var_ctx.infer_uses(&mut errors, &globals, &mut lt_ctx, &self.result);
-    errors.push(self.var_ctx.check_types(&globals, &mut lt_ctx));
+    self.var_ctx.infer_uses(&mut errors, globals, lt_ctx, &self.result);
+    errors.push(self.var_ctx.check_types(globals, lt_ctx));
 
     errors.push(lt_ctx.check_satisfiable(Some(Side::Internal), &lt_ctx.ex_order, &lt_ctx.in_order));
 
@@ -154,28 +285,46 @@ impl RuleDef {
 }
 
 impl NetDef {
+  fn infer_contract(&mut self, globals: &GlobalCtx) {
+    if self.nodes.iter().any(|node| globals.components.get(node.component).is_err()) {
+      return;
+    }
+    let mut errors = ErrorGroup::default();
+    let mut var_ctx = self.var_ctx.clone();
+    let mut lt_ctx = self.lt_ctx.clone();
+    for (i, &(var, label)) in self.free_ports.iter().enumerate() {
+      var_ctx.add_use(var, PortLabel(!label.0, label.1, label.2), Site::Free(i))
+    }
+    var_ctx.infer_uses(&mut errors, globals, &mut lt_ctx, &self.nodes);
+    errors.push(var_ctx.check_types(globals, &mut lt_ctx));
+    if !errors.is_empty() {
+      return;
+    }
+    let free = self.lt_ctx.lifetimes.len();
+    let needs = lt_ctx.in_order.omit(&|lt| lt >= free);
+    self.lt_ctx.ex_order.import_because(&needs, |lt| lt, Origin::Inferred);
+  }
+
   fn define(&mut self, globals: &mut GlobalCtx) -> Result<(), Error> {
     let mut errors = ErrorGroup::default();
 
     for (_, port) in &self.free_ports {
-      errors.push(globals.types.get(port.0));
-      errors.push(self.lt_ctx.lifetimes.get(port.1));
+      errors.push(globals.types.get(port.0).map_err(|err| err.at(port.2)));
+      errors.push(self.lt_ctx.lifetimes.get(port.1).map_err(|err| err.at(port.2)));
     }
 
     errors.push(self.lt_ctx.check_contract_satisfiable());
 
-    errors.push(globals.components.try_define(self.id, || ComponentInfo {
+    errors.push(globals.components.try_define(self.id, self.span, || ComponentInfo {
       lt_ctx: self.lt_ctx.clone(),
       ports: self.free_ports.iter().map(|x| x.1).collect(),
+      port_names: self.free_ports.iter().map(|x| Some(self.var_ctx.vars.name(x.0).to_owned())).collect(),
     }));
 
     let name = globals.components.name(self.id);
     let ctx = display!("in net `{name}`:");
 
-    errors.report(ctx).map_err(|err| {
-      globals.components.poison(self.id);
-      err
-    })
+    errors.report(ctx).inspect_err(|_| globals.components.poison(self.id))
   }
 
   fn check(&mut self, globals: &GlobalCtx) -> Result<(), Error> {
@@ -185,17 +334,17 @@ impl NetDef {
     let ctx = &display!("in net `{name}`:");
 
     for node in &self.nodes {
-      errors.push(globals.components.get(node.component));
+      errors.push(globals.components.get(node.component).map_err(|err| err.at(node.span)));
     }
 
     errors.report(ctx)?;
 
-    for &(var, label) in &self.free_ports {
-      self.var_ctx.vars[var].uses.push(PortLabel(!label.0, label.1))
+    for (i, &(var, label)) in self.free_ports.iter().enumerate() {
+      self.var_ctx.add_use(var, PortLabel(!label.0, label.1, label.2), Site::Free(i))
     }
 
-    self.var_ctx.infer_uses(&mut errors, &globals, &mut self.lt_ctx, &self.nodes);
-    errors.push(self.var_ctx.check_types(&globals, &mut self.lt_ctx));
+    self.var_ctx.infer_uses(&mut errors, globals, &mut self.lt_ctx, &self.nodes);
+    errors.push(self.var_ctx.check_types(globals, &mut self.lt_ctx));
 
     errors.push(self.lt_ctx.check_satisfiable(Some(Side::Internal), &self.lt_ctx.ex_order, &self.lt_ctx.in_order));
 
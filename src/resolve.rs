@@ -0,0 +1,149 @@
+use std::{
+  collections::HashMap,
+  env, fs, io,
+  path::{Component, Path, PathBuf},
+};
+
+/// Reads the files of a program: the root file given by path, and the files
+/// it includes, whose paths are relative to the including file.
+pub trait Resolver {
+  /// The canonical form of `path`, which identifies the file it names, so
+  /// that a file included along several paths is loaded once.
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+  /// Reads the file named by `path`, whose canonical form is `canonical`.
+  fn read(&self, path: &Path, canonical: &Path) -> io::Result<Vec<u8>>;
+
+  /// Replaces the contents of the file whose canonical form is `canonical`,
+  /// as when applying fixes. By default, files cannot be written.
+  fn write(&self, canonical: &Path, contents: &str) -> io::Result<()> {
+    let _ = (canonical, contents);
+    Err(io::Error::new(io::ErrorKind::Unsupported, "files cannot be written"))
+  }
+
+  /// The paths that an `include` of `relative` in the file at `including` may
+  /// name, in order of preference; the first that can be read is loaded.
+  fn include_paths(&self, including: &Path, relative: &str) -> Vec<PathBuf> {
+    vec![tidy(&including.parent().unwrap_or(Path::new("")).join(relative))]
+  }
+}
+
+/// Reads files from the file system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSystem;
+
+impl Resolver for FileSystem {
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+    path.canonicalize()
+  }
+
+  fn read(&self, path: &Path, _: &Path) -> io::Result<Vec<u8>> {
+    fs::read(path)
+  }
+
+  fn write(&self, canonical: &Path, contents: &str) -> io::Result<()> {
+    fs::write(canonical, contents)
+  }
+}
+
+impl Resolver for HashMap<PathBuf, String> {
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+    Ok(normalize(path))
+  }
+
+  fn read(&self, _: &Path, canonical: &Path) -> io::Result<Vec<u8>> {
+    match self.iter().find(|(path, _)| normalize(path) == canonical) {
+      Some((_, contents)) => Ok(contents.clone().into_bytes()),
+      None => Err(io::Error::new(io::ErrorKind::NotFound, "no such file in memory")),
+    }
+  }
+}
+
+/// Looks for included files in `dirs` when they are not found relative to the
+/// including file, as for `-I` flags.
+#[derive(Debug, Clone, Default)]
+pub struct SearchPaths<R> {
+  pub base: R,
+  pub dirs: Vec<PathBuf>,
+}
+
+impl<R> SearchPaths<R> {
+  /// Searches `dirs` and then the directories listed in the `INLT_PATH`
+  /// environment variable, separated as in `PATH`.
+  pub fn from_env(base: R, mut dirs: Vec<PathBuf>) -> Self {
+    if let Some(paths) = env::var_os("INLT_PATH") {
+      dirs.extend(env::split_paths(&paths).filter(|dir| !dir.as_os_str().is_empty()));
+    }
+    SearchPaths { base, dirs }
+  }
+}
+
+impl<R: Resolver> Resolver for SearchPaths<R> {
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+    self.base.canonicalize(path)
+  }
+
+  fn read(&self, path: &Path, canonical: &Path) -> io::Result<Vec<u8>> {
+    self.base.read(path, canonical)
+  }
+
+  fn write(&self, canonical: &Path, contents: &str) -> io::Result<()> {
+    self.base.write(canonical, contents)
+  }
+
+  fn include_paths(&self, including: &Path, relative: &str) -> Vec<PathBuf> {
+    let mut paths = self.base.include_paths(including, relative);
+    paths.extend(self.dirs.iter().map(|dir| tidy(&dir.join(relative))));
+    paths
+  }
+}
+
+/// Reads the files whose canonical paths are in `files` from there, such as
+/// the unsaved contents of open editor buffers, and any other from `base`.
+#[derive(Debug, Clone, Copy)]
+pub struct Overlay<'a, R> {
+  pub base: R,
+  pub files: &'a HashMap<PathBuf, String>,
+}
+
+impl<R: Resolver> Resolver for Overlay<'_, R> {
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+    self.base.canonicalize(path)
+  }
+
+  fn read(&self, path: &Path, canonical: &Path) -> io::Result<Vec<u8>> {
+    match self.files.get(canonical) {
+      Some(contents) => Ok(contents.clone().into_bytes()),
+      None => self.base.read(path, canonical),
+    }
+  }
+
+  fn write(&self, canonical: &Path, contents: &str) -> io::Result<()> {
+    if self.files.contains_key(canonical) {
+      return Err(io::Error::new(io::ErrorKind::Unsupported, "the file is held in memory"));
+    }
+    self.base.write(canonical, contents)
+  }
+
+  fn include_paths(&self, including: &Path, relative: &str) -> Vec<PathBuf> {
+    self.base.include_paths(including, relative)
+  }
+}
+
+fn tidy(path: &Path) -> PathBuf {
+  path.components().filter(|c| *c != Component::CurDir).collect()
+}
+
+fn normalize(path: &Path) -> PathBuf {
+  let mut normal = PathBuf::new();
+  for component in path.components() {
+    match component {
+      Component::CurDir => {}
+      Component::ParentDir if matches!(normal.components().next_back(), Some(Component::Normal(_))) => {
+        normal.pop();
+      }
+      component => normal.push(component),
+    }
+  }
+  normal
+}
@@ -0,0 +1,181 @@
+mod flatten;
+mod readback;
+
+use std::{
+  collections::HashMap,
+  fmt::{self, Debug},
+};
+
+use nohash_hasher::IntMap;
+
+use crate::{
+  err,
+  error::Error,
+  globals::{Component, GlobalCtx},
+  index_vec::IndexVec,
+  new_index,
+  program::{NetDef, Program},
+};
+
+use self::flatten::{FlatNet, FlatRule};
+
+pub struct Runtime<'p> {
+  program: &'p Program,
+  nets: IntMap<Component, &'p NetDef>,
+  rules: HashMap<(Component, Component), (usize, bool)>,
+  flat_nets: IntMap<Component, FlatNet>,
+  flat_rules: HashMap<usize, FlatRule>,
+}
+
+new_index!(pub AgentId "agent");
+
+#[derive(Debug, Clone, Default)]
+pub struct Net {
+  pub agents: IndexVec<AgentId, Option<Agent>>,
+  pub free: Vec<Port>,
+  vacant: Vec<AgentId>,
+  redexes: Vec<(AgentId, AgentId)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Agent {
+  pub component: Component,
+  pub ports: Vec<Port>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Port {
+  Free(usize),
+  Agent(AgentId, usize),
+}
+
+impl<'p> Runtime<'p> {
+  pub fn new(program: &'p Program) -> Self {
+    let nets = program.nets.iter().map(|net| (net.id, net)).collect();
+    let mut rules = HashMap::new();
+    for (index, rule) in program.rules.iter().enumerate() {
+      let (a, b) = (rule.a.component, rule.b.component);
+      rules.entry((a, b)).or_insert((index, false));
+      rules.entry((b, a)).or_insert((index, true));
+    }
+    Runtime { program, nets, rules, flat_nets: IntMap::default(), flat_rules: HashMap::new() }
+  }
+
+  pub fn globals(&self) -> &'p GlobalCtx {
+    &self.program.globals
+  }
+
+  pub fn net(&self, name: &str) -> Option<Component> {
+    self.program.nets.iter().map(|net| net.id).find(|&id| self.globals().component_matches(id, name))
+  }
+
+  pub fn instantiate(&mut self, id: Component) -> Result<Net, Error> {
+    let flat = self.flatten_net(id)?;
+    let mut net = Net { free: (0..flat.free.len()).map(Port::Free).collect(), ..Net::default() };
+    net.build(flat, flat.free.iter().enumerate().map(|(i, &wire)| (wire, Port::Free(i))), None);
+    Ok(net)
+  }
+
+  pub fn reduce(&mut self, net: &mut Net, limit: Option<usize>) -> Result<usize, Error> {
+    let mut interactions = 0;
+    while let Some((a, b)) = net.redexes.pop() {
+      if limit == Some(interactions) {
+        net.redexes.push((a, b));
+        Err(err!("no normal form was reached within {interactions} interactions"))?
+      }
+      let a_component = net.agent(a).component;
+      let b_component = net.agent(b).component;
+      let Some(&(rule, swapped)) = self.rules.get(&(a_component, b_component)) else {
+        net.redexes.push((a, b));
+        let components = &self.globals().components;
+        Err(err!("no rule for `{}` and `{}`", components.name(a_component), components.name(b_component)))?
+      };
+      let (a, b) = if swapped { (b, a) } else { (a, b) };
+      let rule = self.flatten_rule(rule)?;
+      let bound = rule.a_aux.iter().enumerate().map(|(i, &wire)| (wire, Port::Agent(a, i + 1)));
+      let bound = bound.chain(rule.b_aux.iter().enumerate().map(|(i, &wire)| (wire, Port::Agent(b, i + 1))));
+      net.build(&rule.net, bound, Some([a, b]));
+      net.remove(a);
+      net.remove(b);
+      interactions += 1;
+    }
+    Ok(interactions)
+  }
+}
+
+impl Net {
+  pub fn agent(&self, id: AgentId) -> &Agent {
+    self.agents[id].as_ref().unwrap()
+  }
+
+  pub fn live_agents(&self) -> impl Iterator<Item = (AgentId, &Agent)> {
+    self.agents.iter().filter_map(|(id, agent)| Some((id, agent.as_ref()?)))
+  }
+
+  pub fn target(&self, port: Port) -> Port {
+    match port {
+      Port::Free(i) => self.free[i],
+      Port::Agent(id, i) => self.agent(id).ports[i],
+    }
+  }
+
+  fn set_target(&mut self, port: Port, target: Port) {
+    match port {
+      Port::Free(i) => self.free[i] = target,
+      Port::Agent(id, i) => self.agents[id].as_mut().unwrap().ports[i] = target,
+    }
+  }
+
+  fn link(&mut self, a: Port, b: Port) {
+    self.set_target(a, b);
+    self.set_target(b, a);
+    if let (Port::Agent(a, 0), Port::Agent(b, 0)) = (a, b) {
+      self.redexes.push((a, b));
+    }
+  }
+
+  fn alloc(&mut self, agent: Agent) -> AgentId {
+    if let Some(id) = self.vacant.pop() {
+      self.agents[id] = Some(agent);
+      id
+    } else {
+      self.agents.push(Some(agent))
+    }
+  }
+
+  fn remove(&mut self, id: AgentId) {
+    self.agents[id] = None;
+    self.vacant.push(id);
+  }
+
+  fn build(&mut self, flat: &FlatNet, bound: impl Iterator<Item = (usize, Port)>, dying: Option<[AgentId; 2]>) {
+    let resolve = |net: &Net, port: Port| match port {
+      Port::Agent(id, _) if dying.is_some_and(|d| d.contains(&id)) => net.target(port),
+      _ => port,
+    };
+    let mut pending = vec![None; flat.wires];
+    let mut connect = |net: &mut Net, wire: usize, port: Port| match pending[wire].take() {
+      Some(other) => {
+        let (a, b) = (resolve(net, other), resolve(net, port));
+        net.link(a, b);
+      }
+      None => pending[wire] = Some(port),
+    };
+    for (wire, port) in bound {
+      connect(self, wire, port);
+    }
+    for node in &flat.nodes {
+      let ports = vec![Port::Free(usize::MAX); node.ports.len()];
+      let id = self.alloc(Agent { component: node.component, ports });
+      for (i, &wire) in node.ports.iter().enumerate() {
+        connect(self, wire, Port::Agent(id, i));
+      }
+    }
+  }
+}
+
+impl Debug for AgentId {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "@{}", self.0)
+  }
+}
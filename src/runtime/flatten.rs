@@ -0,0 +1,138 @@
+use nohash_hasher::IntMap;
+
+use super::Runtime;
+use crate::{
+  err,
+  error::Error,
+  globals::Component,
+  program::{Node, RuleDef},
+  vars::Var,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct FlatNet {
+  pub wires: usize,
+  pub free: Vec<usize>,
+  pub nodes: Vec<FlatNode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlatNode {
+  pub component: Component,
+  pub ports: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FlatRule {
+  pub a_aux: Vec<usize>,
+  pub b_aux: Vec<usize>,
+  pub net: FlatNet,
+}
+
+impl<'p> Runtime<'p> {
+  pub(super) fn flatten_net(&mut self, id: Component) -> Result<&FlatNet, Error> {
+    if !self.flat_nets.contains_key(&id) {
+      let net = self.nets[&id];
+      let mut flattener = Flattener::default();
+      let mut vars = IntMap::default();
+      let mut free = net.free_ports.iter().map(|&(var, _)| flattener.wire(&mut vars, var)).collect();
+      flattener.flatten(self, &mut vars, &net.nodes, &mut vec![id])?;
+      let mut flat = flattener.finish(&mut [&mut free]);
+      flat.free = free;
+      self.flat_nets.insert(id, flat);
+    }
+    Ok(&self.flat_nets[&id])
+  }
+
+  pub(super) fn flatten_rule(&mut self, index: usize) -> Result<&FlatRule, Error> {
+    if !self.flat_rules.contains_key(&index) {
+      let rule: &RuleDef = &self.program.rules[index];
+      let mut flattener = Flattener::default();
+      let mut vars = IntMap::default();
+      let mut a_aux = rule.a.ports[1..].iter().map(|&var| flattener.wire(&mut vars, var)).collect();
+      let mut b_aux = rule.b.ports[1..].iter().map(|&var| flattener.wire(&mut vars, var)).collect();
+      flattener.flatten(self, &mut vars, &rule.result, &mut vec![])?;
+      let net = flattener.finish(&mut [&mut a_aux, &mut b_aux]);
+      self.flat_rules.insert(index, FlatRule { a_aux, b_aux, net });
+    }
+    Ok(&self.flat_rules[&index])
+  }
+}
+
+#[derive(Default)]
+struct Flattener {
+  parents: Vec<usize>,
+  nodes: Vec<FlatNode>,
+}
+
+impl Flattener {
+  fn wire(&mut self, vars: &mut IntMap<Var, usize>, var: Var) -> usize {
+    *vars.entry(var).or_insert_with(|| {
+      self.parents.push(self.parents.len());
+      self.parents.len() - 1
+    })
+  }
+
+  fn bind(&mut self, vars: &mut IntMap<Var, usize>, var: Var, wire: usize) {
+    if let Some(&existing) = vars.get(&var) {
+      let (a, b) = (self.root(existing), self.root(wire));
+      self.parents[a] = b;
+    } else {
+      vars.insert(var, wire);
+    }
+  }
+
+  fn root(&mut self, mut wire: usize) -> usize {
+    while self.parents[wire] != wire {
+      self.parents[wire] = self.parents[self.parents[wire]];
+      wire = self.parents[wire];
+    }
+    wire
+  }
+
+  fn flatten(
+    &mut self,
+    runtime: &Runtime,
+    vars: &mut IntMap<Var, usize>,
+    nodes: &[Node],
+    stack: &mut Vec<Component>,
+  ) -> Result<(), Error> {
+    for node in nodes {
+      let ports = node.ports.iter().map(|&var| self.wire(vars, var)).collect::<Vec<_>>();
+      if let Some(&net) = runtime.nets.get(&node.component) {
+        if stack.contains(&node.component) {
+          Err(
+            err!("cannot run recursive net `{}`", runtime.program.globals.components.name(node.component))
+              .at(node.span),
+          )?
+        }
+        let mut inner = IntMap::default();
+        for (&(var, _), &wire) in net.free_ports.iter().zip(&ports) {
+          self.bind(&mut inner, var, wire);
+        }
+        stack.push(node.component);
+        self.flatten(runtime, &mut inner, &net.nodes, stack)?;
+        stack.pop();
+      } else {
+        self.nodes.push(FlatNode { component: node.component, ports });
+      }
+    }
+    Ok(())
+  }
+
+  fn finish(mut self, free: &mut [&mut Vec<usize>]) -> FlatNet {
+    let mut renumber = vec![usize::MAX; self.parents.len()];
+    let mut wires = 0;
+    let mut nodes = std::mem::take(&mut self.nodes);
+    let ports = free.iter_mut().flat_map(|x| x.iter_mut()).chain(nodes.iter_mut().flat_map(|x| &mut x.ports));
+    for wire in ports {
+      let root = self.root(*wire);
+      if renumber[root] == usize::MAX {
+        renumber[root] = wires;
+        wires += 1;
+      }
+      *wire = renumber[root];
+    }
+    FlatNet { wires, free: vec![], nodes }
+  }
+}
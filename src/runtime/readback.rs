@@ -0,0 +1,105 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fmt::{self, Display},
+};
+
+use super::{AgentId, Net, Port, Runtime};
+use crate::{globals::Component, util::DisplayFn};
+
+impl<'p> Runtime<'p> {
+  pub fn show_net<'a>(&'a self, net: &'a Net, id: Component) -> impl Display + 'a {
+    DisplayFn(move |f| {
+      let globals = self.globals();
+      let names = self.wire_names(net, id);
+      write!(f, "{}", globals.show_net_header(id, |i| &names[&wire(net, Port::Free(i))]))?;
+      write!(f, " {{")?;
+      for (agent_id, agent) in net.live_agents() {
+        write!(f, "\n  {}(", globals.components.name(agent.component))?;
+        for i in 0..agent.ports.len() {
+          let name = &names[&wire(net, Port::Agent(agent_id, i))];
+          write!(f, "{}{name}", if i == 0 { "" } else { ", " })?;
+        }
+        write!(f, ")")?;
+      }
+      write!(f, "\n}}")
+    })
+  }
+
+  pub fn show_trees<'a>(&'a self, net: &'a Net, id: Component) -> impl Display + 'a {
+    DisplayFn(move |f| {
+      let mut trees = Trees { runtime: self, net, names: self.wire_names(net, id), visited: HashSet::new() };
+      let mut first = true;
+      for (i, &target) in net.free.iter().enumerate() {
+        if matches!(target, Port::Agent(id, 0) if !trees.visited.contains(&id)) {
+          write!(f, "{}{} = ", if first { "" } else { "\n" }, trees.names[&wire(net, Port::Free(i))])?;
+          trees.show_tree(f, target)?;
+          first = false;
+        }
+      }
+      for (agent_id, _) in net.live_agents() {
+        if !trees.visited.contains(&agent_id) {
+          write!(f, "{}", if first { "" } else { "\n" })?;
+          trees.show_agent(f, agent_id, false)?;
+          first = false;
+        }
+      }
+      Ok(())
+    })
+  }
+
+  fn wire_names(&self, net: &Net, id: Component) -> HashMap<Port, String> {
+    let def = self.nets[&id];
+    let mut names = HashMap::new();
+    for (i, &(var, _)) in def.free_ports.iter().enumerate() {
+      names.entry(wire(net, Port::Free(i))).or_insert_with(|| def.var_ctx.vars.name(var).to_owned());
+    }
+    let taken = names.values().cloned().collect::<HashSet<_>>();
+    let mut fresh = (0..).map(|i| format!("x{i}")).filter(|name| !taken.contains(name));
+    for (agent_id, agent) in net.live_agents() {
+      for i in 0..agent.ports.len() {
+        names.entry(wire(net, Port::Agent(agent_id, i))).or_insert_with(|| fresh.next().unwrap());
+      }
+    }
+    names
+  }
+}
+
+fn wire(net: &Net, port: Port) -> Port {
+  port.min(net.target(port))
+}
+
+struct Trees<'a, 'p> {
+  runtime: &'a Runtime<'p>,
+  net: &'a Net,
+  names: HashMap<Port, String>,
+  visited: HashSet<AgentId>,
+}
+
+impl Trees<'_, '_> {
+  fn show_tree(&mut self, f: &mut fmt::Formatter, target: Port) -> fmt::Result {
+    match target {
+      Port::Agent(id, 0) if !self.visited.contains(&id) => self.show_agent(f, id, true),
+      _ => write!(f, "{}", self.names[&wire(self.net, target)]),
+    }
+  }
+
+  fn show_agent(&mut self, f: &mut fmt::Formatter, id: AgentId, nested: bool) -> fmt::Result {
+    self.visited.insert(id);
+    let agent = self.net.agent(id);
+    write!(f, "{}", self.runtime.globals().components.name(agent.component))?;
+    if nested && agent.ports.len() == 1 {
+      return Ok(());
+    }
+    write!(f, "(")?;
+    if !nested {
+      write!(f, "{}", self.names[&wire(self.net, Port::Agent(id, 0))])?;
+    }
+    for i in 1..agent.ports.len() {
+      if !nested || i != 1 {
+        write!(f, ", ")?;
+      }
+      self.show_tree(f, agent.ports[i])?;
+    }
+    write!(f, ")")
+  }
+}
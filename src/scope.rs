@@ -4,10 +4,14 @@ use std::{
 };
 
 use crate::{
+  err,
   error::Error,
   index_vec::{Idx, IndexVec},
+  span::Span,
 };
 
+/// Named entities, indexed by `K`. An entity may be referenced before it is
+/// defined, so it may be undefined, or poisoned by an error in its definition.
 #[derive(Debug, Clone)]
 pub struct Scope<K: Idx, T> {
   vec: IndexVec<K, Definition<T>>,
@@ -16,6 +20,7 @@ pub struct Scope<K: Idx, T> {
 #[derive(Debug, Clone)]
 struct Definition<T> {
   name: String,
+  span: Option<Span>,
   state: DefinitionState<T>,
 }
 
@@ -34,64 +39,83 @@ impl<K: Idx, T> Scope<K, T> {
   pub fn get(&self, index: K) -> Result<&T, Error> {
     match &self.vec[index] {
       Definition { state: DefinitionState::Defined(value), .. } => Ok(value),
-      Definition { name, state: DefinitionState::Undefined } => Err(Self::undefined_error(name)),
-      Definition { name, state: DefinitionState::Poisoned } => Err(Self::poisoned_error(name)),
+      Definition { name, state: DefinitionState::Undefined, .. } => Err(Self::undefined_error(name)),
+      Definition { name, state: DefinitionState::Poisoned, .. } => Err(Self::poisoned_error(name)),
     }
   }
 
-  pub fn poison(&mut self, index: K) {
+  pub(crate) fn poison(&mut self, index: K) {
     self.vec[index].state = DefinitionState::Poisoned;
   }
 
-  pub fn push(&mut self, name: String, value: Option<T>) -> K {
-    self.vec.push(Definition { name, state: value.map(DefinitionState::Defined).unwrap_or(DefinitionState::Undefined) })
+  pub(crate) fn push(&mut self, name: String, span: Option<Span>, value: Option<T>) -> K {
+    let state = value.map(DefinitionState::Defined).unwrap_or(DefinitionState::Undefined);
+    self.vec.push(Definition { name, span, state })
+  }
+
+  /// The index named `name`, if any.
+  pub fn lookup(&self, name: &str) -> Option<K> {
+    self.vec.iter().find(|(_, def)| def.name == name).map(|(index, _)| index)
   }
 
   pub fn name(&self, index: K) -> &str {
     &self.vec[index].name
   }
 
+  /// The span of the definition of `index`, if it has been defined.
+  pub fn span(&self, index: K) -> Option<Span> {
+    self.vec[index].span
+  }
+
   pub fn poisoned(&self, index: K) -> bool {
     matches!(self.vec[index].state, DefinitionState::Poisoned)
   }
 
   pub fn iter(&self) -> impl Iterator<Item = (K, &str, &T)> {
-    self.vec.iter().filter_map(|(k, Definition { name, state })| match state {
+    self.vec.iter().filter_map(|(k, Definition { name, state, .. })| match state {
       DefinitionState::Defined(value) => Some((k, &**name, value)),
       _ => None,
     })
   }
 
-  pub fn iter_mut(&mut self) -> impl Iterator<Item = (K, &str, &mut T)> {
-    self.vec.iter_mut().filter_map(|(k, Definition { name, state })| match state {
+  #[allow(unused)]
+  pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (K, &str, &mut T)> {
+    self.vec.iter_mut().filter_map(|(k, Definition { name, state, .. })| match state {
       DefinitionState::Defined(value) => Some((k, &**name, value)),
       _ => None,
     })
   }
 
-  pub fn expect_undefined(&self, index: K) -> Result<(), Error> {
+  pub(crate) fn expect_undefined(&self, index: K, span: Span) -> Result<(), Error> {
     let def = &self.vec[index];
     if matches!(def.state, DefinitionState::Undefined) {
       Ok(())
     } else {
-      Err(Error::from(format_args!("duplicate definition of {} `{}`", K::KIND, def.name)))
+      let err = err!("duplicate definition of {} `{}`", K::KIND, def.name).at(span);
+      Err(match def.span {
+        Some(prev) => err.label(prev, "previously defined here"),
+        None => err,
+      })
     }
   }
 
-  pub fn try_define(&mut self, index: K, value: impl FnOnce() -> T) -> Result<(), Error> {
-    if let Err(err) = self.expect_undefined(index) {
+  pub(crate) fn try_define(&mut self, index: K, span: Span, value: impl FnOnce() -> T) -> Result<(), Error> {
+    if let Err(err) = self.expect_undefined(index, span) {
       self.poison(index);
       Err(err)
     } else {
+      self.vec[index].span = Some(span);
       self.vec[index].state = DefinitionState::Defined(value());
       Ok(())
     }
   }
 
-  pub fn or_define(&mut self, index: K, value: impl FnOnce() -> T) -> &mut T {
-    match &mut self.vec[index].state {
+  pub(crate) fn or_define(&mut self, index: K, span: Span, value: impl FnOnce() -> T) -> &mut T {
+    let def = &mut self.vec[index];
+    match &mut def.state {
       DefinitionState::Defined(value) => value,
       state => {
+        def.span = Some(span);
         *state = DefinitionState::Defined(value());
         let DefinitionState::Defined(value) = state else { unreachable!() };
         value
@@ -115,7 +139,7 @@ pub struct ScopeBuilder<'i, K: Idx, T> {
 
 impl<'i, K: Idx, T> ScopeBuilder<'i, K, T> {
   pub fn get(&mut self, name: &'i str) -> K {
-    *self.lookup.entry(name).or_insert_with(|| self.scope.push(name.to_owned(), None))
+    *self.lookup.entry(name).or_insert_with(|| self.scope.push(name.to_owned(), None, None))
   }
 
   pub fn ensure_empty(&self) {
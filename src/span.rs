@@ -0,0 +1,85 @@
+use std::{fmt, ops::Index, path::PathBuf};
+
+use highlight_error::highlight_error;
+
+use crate::{index_vec::IndexVec, new_index};
+
+new_index!(pub FileId "file");
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+  pub file: FileId,
+  pub start: usize,
+  pub end: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Files {
+  files: IndexVec<FileId, File>,
+}
+
+#[derive(Debug, Clone)]
+pub struct File {
+  pub name: String,
+  /// The canonical path of the file, if it was read through a resolver rather
+  /// than embedded, like the standard library.
+  pub canonical: Option<PathBuf>,
+  pub contents: String,
+}
+
+impl Span {
+  pub fn new(file: FileId, start: usize, end: usize) -> Self {
+    Span { file, start, end }
+  }
+
+  /// The smallest span covering both `self` and `other`, which must be in the
+  /// same file.
+  pub fn to(self, other: Span) -> Span {
+    debug_assert!(self.file == other.file);
+    Span { file: self.file, start: self.start.min(other.start), end: self.end.max(other.end) }
+  }
+}
+
+impl Files {
+  pub fn add(&mut self, name: String, canonical: Option<PathBuf>, contents: String) -> FileId {
+    self.files.push(File { name, canonical, contents })
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (FileId, &File)> {
+    self.files.iter()
+  }
+}
+
+impl File {
+  /// Returns the one-indexed line and column of a byte offset.
+  pub fn line_col(&self, offset: usize) -> (usize, usize) {
+    let before = &self.contents[..offset.min(self.contents.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before.rfind('\n').map(|i| offset - i).unwrap_or(offset + 1);
+    (line, col)
+  }
+
+  pub fn highlight(&self, span: Span) -> String {
+    highlight_error(span.start, span.end, &self.contents)
+  }
+}
+
+impl Index<FileId> for Files {
+  type Output = File;
+
+  fn index(&self, index: FileId) -> &Self::Output {
+    &self.files[index]
+  }
+}
+
+impl fmt::Debug for FileId {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "f{}", self.0)
+  }
+}
+
+impl fmt::Debug for Span {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:?}@{}..{}", self.file, self.start, self.end)
+  }
+}
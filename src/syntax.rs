@@ -0,0 +1,434 @@
+use std::fmt;
+
+mod parse;
+
+pub(crate) use self::parse::Parser;
+pub use self::parse::{parse, SyntaxError};
+
+#[derive(Debug, Clone)]
+pub struct SourceFile<'i> {
+  pub items: Vec<Item<'i>>,
+  /// The trivia after the last item.
+  pub end: Vec<Trivia<'i>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trivia<'i> {
+  pub kind: TriviaKind,
+  pub text: &'i str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+  Whitespace,
+  /// A line comment, without the newline ending it.
+  Comment,
+  /// Source skipped after a syntax error, up to the next item.
+  Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'i> {
+  /// The trivia between the previous token and this one.
+  pub leading: Vec<Trivia<'i>>,
+  /// The byte offset of the token in the source.
+  pub start: usize,
+  pub text: &'i str,
+}
+
+impl Token<'_> {
+  pub fn end(&self) -> usize {
+    self.start + self.text.len()
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum Item<'i> {
+  Include(Include<'i>),
+  Use(Use<'i>),
+  Type(TypeDef<'i>),
+  Agent(AgentDef<'i>),
+  Rule(RuleDef<'i>),
+  Net(NetDef<'i>),
+}
+
+/// `include path`, where the path is the rest of the line.
+#[derive(Debug, Clone)]
+pub struct Include<'i> {
+  pub keyword: Token<'i>,
+  pub path: Token<'i>,
+}
+
+/// `use module::Name`, or `use module::{Name, Other as Alias, ...}`.
+#[derive(Debug, Clone)]
+pub struct Use<'i> {
+  pub keyword: Token<'i>,
+  pub module: Token<'i>,
+  pub colons: Token<'i>,
+  /// The braces around the names, if any.
+  pub braces: Option<(Token<'i>, Token<'i>)>,
+  pub names: Vec<(UseName<'i>, Option<Token<'i>>)>,
+}
+
+/// `Name`, or `Name as Alias`.
+#[derive(Debug, Clone)]
+pub struct UseName<'i> {
+  pub name: Token<'i>,
+  pub alias: Option<(Token<'i>, Token<'i>)>,
+}
+
+/// `type Name: +`
+#[derive(Debug, Clone)]
+pub struct TypeDef<'i> {
+  /// The `pub` marker, if the type is visible outside its module.
+  pub vis: Option<Token<'i>>,
+  pub keyword: Token<'i>,
+  pub ty: TypeRef<'i>,
+  pub colon: Token<'i>,
+  pub polarity: Token<'i>,
+}
+
+/// `agent[...] Name(Type'lt, ...)`, whose ports may be named, as in
+/// `Name(port: Type'lt, ...)`.
+#[derive(Debug, Clone)]
+pub struct AgentDef<'i> {
+  pub vis: Option<Token<'i>>,
+  pub keyword: Token<'i>,
+  pub lt_ctx: Option<LtCtx<'i>>,
+  pub node: NodeLike<'i, AgentPort<'i>>,
+}
+
+/// `rule A(...) B(...) { ... }`
+#[derive(Debug, Clone)]
+pub struct RuleDef<'i> {
+  pub keyword: Token<'i>,
+  pub a: Node<'i>,
+  pub b: Node<'i>,
+  pub body: Body<'i>,
+}
+
+/// `net[...] name(var: Type'lt, ...) { ... }`
+#[derive(Debug, Clone)]
+pub struct NetDef<'i> {
+  pub vis: Option<Token<'i>>,
+  pub keyword: Token<'i>,
+  pub lt_ctx: Option<LtCtx<'i>>,
+  pub node: NodeLike<'i, FreePort<'i>>,
+  pub body: Body<'i>,
+}
+
+/// `[...]`, in the order written.
+#[derive(Debug, Clone)]
+pub struct LtCtx<'i> {
+  pub open: Token<'i>,
+  pub parts: Vec<LtPart<'i>>,
+  pub close: Token<'i>,
+}
+
+#[derive(Debug, Clone)]
+pub enum LtPart<'i> {
+  /// The `_` of a net whose contract is to be inferred.
+  Hole(Token<'i>),
+  Decl(LtDecl<'i>),
+  /// One of `,`, `<`, `<=`, `>`, `>=`, or the `|` separating external and
+  /// internal lifetimes.
+  Op(Token<'i>),
+}
+
+/// `'lt`, or `'lt?` if its side is flipped.
+#[derive(Debug, Clone)]
+pub struct LtDecl<'i> {
+  pub lt: Token<'i>,
+  pub question: Option<Token<'i>>,
+}
+
+/// `Name(port, ...)`, where each port is followed by its comma, if any.
+#[derive(Debug, Clone)]
+pub struct NodeLike<'i, T> {
+  pub name: Token<'i>,
+  pub open: Token<'i>,
+  pub ports: Vec<(T, Option<Token<'i>>)>,
+  pub close: Token<'i>,
+}
+
+/// A node in a rule or net, whose ports are variables or nested nodes. Its
+/// name may be qualified by a module, as in `nat::Succ`.
+pub type Node<'i> = NodeLike<'i, Arg<'i>>;
+
+/// A port of a node, which may be given for the port of the agent with the
+/// same name rather than by position, as in `Lam(ret: o, ...)`.
+#[derive(Debug, Clone)]
+pub struct Arg<'i> {
+  /// The name of the port and its colon.
+  pub name: Option<(Token<'i>, Token<'i>)>,
+  pub port: Port<'i>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Port<'i> {
+  Var(Token<'i>),
+  /// A node whose principal port, written `_`, is connected to this port by
+  /// an anonymous wire, as in `Succ(z, Succ(_, x))`.
+  Node(Box<Node<'i>>),
+}
+
+/// `name: Type'lt`, or just `Type'lt` if the port is unnamed.
+#[derive(Debug, Clone)]
+pub struct AgentPort<'i> {
+  pub name: Option<(Token<'i>, Token<'i>)>,
+  pub label: PortLabel<'i>,
+}
+
+/// `Type'lt`, or `!Type'lt`.
+#[derive(Debug, Clone)]
+pub struct PortLabel<'i> {
+  pub ty: TypeRef<'i>,
+  pub lt: Token<'i>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeRef<'i> {
+  pub bang: Option<Token<'i>>,
+  pub name: Token<'i>,
+}
+
+/// `var: Type'lt`
+#[derive(Debug, Clone)]
+pub struct FreePort<'i> {
+  pub var: Token<'i>,
+  pub colon: Token<'i>,
+  pub label: PortLabel<'i>,
+}
+
+/// `{ Node(...) ... }`
+#[derive(Debug, Clone)]
+pub struct Body<'i> {
+  pub open: Token<'i>,
+  pub nodes: Vec<Node<'i>>,
+  pub close: Token<'i>,
+}
+
+/// Syntax made up of tokens, which can be visited in source order.
+pub trait Tokens<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>));
+}
+
+impl<'i> Tokens<'i> for Token<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    f(self)
+  }
+}
+
+impl<'i, T: Tokens<'i>> Tokens<'i> for Option<T> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    if let Some(x) = self {
+      x.tokens(f)
+    }
+  }
+}
+
+impl<'i> Tokens<'i> for SourceFile<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    for item in &self.items {
+      item.tokens(f);
+    }
+  }
+}
+
+impl<'i> Item<'i> {
+  /// The `include`, `use`, `type`, `agent`, `rule`, or `net` keyword.
+  pub fn keyword(&self) -> &Token<'i> {
+    match self {
+      Item::Include(x) => &x.keyword,
+      Item::Use(x) => &x.keyword,
+      Item::Type(x) => &x.keyword,
+      Item::Agent(x) => &x.keyword,
+      Item::Rule(x) => &x.keyword,
+      Item::Net(x) => &x.keyword,
+    }
+  }
+
+  /// The first token of the item: its `pub` marker, if any, or its keyword.
+  pub fn first(&self) -> &Token<'i> {
+    let vis = match self {
+      Item::Type(x) => &x.vis,
+      Item::Agent(x) => &x.vis,
+      Item::Net(x) => &x.vis,
+      Item::Include(_) | Item::Use(_) | Item::Rule(_) => &None,
+    };
+    vis.as_ref().unwrap_or(self.keyword())
+  }
+}
+
+impl<'i> Tokens<'i> for Item<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    match self {
+      Item::Include(x) => {
+        f(&x.keyword);
+        f(&x.path);
+      }
+      Item::Use(x) => {
+        f(&x.keyword);
+        f(&x.module);
+        f(&x.colons);
+        if let Some((open, _)) = &x.braces {
+          f(open);
+        }
+        for (name, comma) in &x.names {
+          f(&name.name);
+          if let Some((as_, alias)) = &name.alias {
+            f(as_);
+            f(alias);
+          }
+          comma.tokens(f);
+        }
+        if let Some((_, close)) = &x.braces {
+          f(close);
+        }
+      }
+      Item::Type(x) => {
+        x.vis.tokens(f);
+        f(&x.keyword);
+        x.ty.tokens(f);
+        f(&x.colon);
+        f(&x.polarity);
+      }
+      Item::Agent(x) => {
+        x.vis.tokens(f);
+        f(&x.keyword);
+        x.lt_ctx.tokens(f);
+        x.node.tokens(f);
+      }
+      Item::Rule(x) => {
+        f(&x.keyword);
+        x.a.tokens(f);
+        x.b.tokens(f);
+        x.body.tokens(f);
+      }
+      Item::Net(x) => {
+        x.vis.tokens(f);
+        f(&x.keyword);
+        x.lt_ctx.tokens(f);
+        x.node.tokens(f);
+        x.body.tokens(f);
+      }
+    }
+  }
+}
+
+impl<'i> Tokens<'i> for LtCtx<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    f(&self.open);
+    for part in &self.parts {
+      match part {
+        LtPart::Hole(token) | LtPart::Op(token) => f(token),
+        LtPart::Decl(decl) => decl.tokens(f),
+      }
+    }
+    f(&self.close);
+  }
+}
+
+impl<'i> Tokens<'i> for LtDecl<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    f(&self.lt);
+    self.question.tokens(f);
+  }
+}
+
+impl<'i, T: Tokens<'i>> Tokens<'i> for NodeLike<'i, T> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    f(&self.name);
+    f(&self.open);
+    for (port, comma) in &self.ports {
+      port.tokens(f);
+      comma.tokens(f);
+    }
+    f(&self.close);
+  }
+}
+
+impl<'i> Tokens<'i> for Arg<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    if let Some((name, colon)) = &self.name {
+      f(name);
+      f(colon);
+    }
+    self.port.tokens(f);
+  }
+}
+
+impl<'i> Tokens<'i> for Port<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    match self {
+      Port::Var(var) => f(var),
+      Port::Node(node) => node.tokens(f),
+    }
+  }
+}
+
+impl<'i> Tokens<'i> for AgentPort<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    if let Some((name, colon)) = &self.name {
+      f(name);
+      f(colon);
+    }
+    self.label.tokens(f);
+  }
+}
+
+impl<'i> Tokens<'i> for PortLabel<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    self.ty.tokens(f);
+    f(&self.lt);
+  }
+}
+
+impl<'i> Tokens<'i> for TypeRef<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    self.bang.tokens(f);
+    f(&self.name);
+  }
+}
+
+impl<'i> Tokens<'i> for FreePort<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    f(&self.var);
+    f(&self.colon);
+    self.label.tokens(f);
+  }
+}
+
+impl<'i> Tokens<'i> for Body<'i> {
+  fn tokens(&self, f: &mut dyn FnMut(&Token<'i>)) {
+    f(&self.open);
+    for node in &self.nodes {
+      node.tokens(f);
+    }
+    f(&self.close);
+  }
+}
+
+fn write_tokens<'i>(f: &mut fmt::Formatter, syntax: &impl Tokens<'i>) -> fmt::Result {
+  let mut result = Ok(());
+  syntax.tokens(&mut |token| {
+    for trivia in &token.leading {
+      result = result.and_then(|_| f.write_str(trivia.text));
+    }
+    result = result.and_then(|_| f.write_str(token.text));
+  });
+  result
+}
+
+impl fmt::Display for SourceFile<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write_tokens(f, self)?;
+    self.end.iter().try_for_each(|trivia| f.write_str(trivia.text))
+  }
+}
+
+impl fmt::Display for Item<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write_tokens(f, self)
+  }
+}
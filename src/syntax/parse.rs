@@ -0,0 +1,396 @@
+use TSPL::Parser as _;
+
+use super::{
+  AgentDef, AgentPort, Arg, Body, FreePort, Include, Item, LtCtx, LtDecl, LtPart, NetDef, Node, NodeLike, Port,
+  PortLabel, RuleDef, SourceFile, Token, Trivia, TriviaKind, TypeDef, TypeRef, Use, UseName,
+};
+
+/// Parses `input` into a syntax tree, along with any syntax errors. An item
+/// with an error is skipped, up to the next line starting with a keyword.
+pub fn parse(input: &str) -> (SourceFile<'_>, Vec<SyntaxError>) {
+  Parser::default().parse_file(input)
+}
+
+/// A syntax error at a byte offset into the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+  pub offset: usize,
+  pub message: String,
+}
+
+const KEYWORDS: &[&str] = &["include", "use", "pub", "type", "agent", "rule", "net"];
+
+#[derive(Default)]
+pub(crate) struct Parser<'i> {
+  input: &'i str,
+  index: usize,
+  trivia: Vec<Trivia<'i>>,
+}
+
+impl<'i> TSPL::Parser<'i> for Parser<'i> {
+  fn input(&mut self) -> &'i str {
+    self.input
+  }
+
+  fn index(&mut self) -> &mut usize {
+    &mut self.index
+  }
+
+  fn expected<T>(&mut self, exp: &str) -> Result<T, String> {
+    Err(format!("expected {exp}"))
+  }
+
+  fn skip_trivia(&mut self) {
+    loop {
+      let (kind, text) = if self.peek_many(2) == Some("//") {
+        (TriviaKind::Comment, self.take_while(|c| c != '\n'))
+      } else if self.peek_one().is_some_and(|c| c.is_ascii_whitespace()) {
+        (TriviaKind::Whitespace, self.take_while(|c| c.is_ascii_whitespace()))
+      } else {
+        break;
+      };
+      self.trivia.push(Trivia { kind, text });
+    }
+  }
+}
+
+impl<'i> Parser<'i> {
+  pub fn parse_file(&mut self, input: &'i str) -> (SourceFile<'i>, Vec<SyntaxError>) {
+    self.input = input;
+    self.index = 0;
+    self.trivia.clear();
+    let mut items = vec![];
+    let mut errors = vec![];
+    self.skip_trivia();
+    while !self.is_eof() {
+      // the trivia before the item, which is taken by its first token
+      let (start, trivia) = (self.index, self.trivia.clone());
+      match self.parse_item() {
+        Ok(item) => items.push(item),
+        Err(message) => {
+          errors.push(SyntaxError { offset: self.index, message });
+          self.trivia = trivia;
+          self.recover(start);
+        }
+      }
+      self.skip_trivia();
+    }
+    (SourceFile { items, end: std::mem::take(&mut self.trivia) }, errors)
+  }
+
+  fn recover(&mut self, start: usize) {
+    let mut end = start;
+    loop {
+      match self.input[end..].find('\n') {
+        Some(i) => end += i + 1,
+        None => break end = self.input.len(),
+      }
+      let line = self.input[end..].trim_start_matches([' ', '\t']);
+      if KEYWORDS.iter().any(|k| line.strip_prefix(k).is_some_and(|x| !x.starts_with(Self::is_name_char))) {
+        break;
+      }
+    }
+    self.trivia.push(Trivia { kind: TriviaKind::Skipped, text: &self.input[start..end] });
+    self.index = end;
+  }
+
+  fn parse_item(&mut self) -> Result<Item<'i>, String> {
+    self.skip_trivia();
+    if self.peek_keyword("include") {
+      let keyword = self.expect("include")?;
+      self.skip_trivia();
+      let start = self.index;
+      let path = self.take_while(|x| x != '\n').trim_end();
+      self.index = start + path.len();
+      return Ok(Item::Include(Include { keyword, path: self.token(start) }));
+    }
+    if self.peek_keyword("use") {
+      return Ok(Item::Use(self.parse_use()?));
+    }
+    if self.peek_keyword("rule") {
+      let keyword = self.expect("rule")?;
+      let a = self.parse_node()?;
+      let b = self.parse_node()?;
+      let body = self.parse_body()?;
+      return Ok(Item::Rule(RuleDef { keyword, a, b, body }));
+    }
+    let vis = if self.peek_keyword("pub") { Some(self.expect("pub")?) } else { None };
+    self.skip_trivia();
+    Ok(if self.peek_keyword("type") {
+      Item::Type(self.parse_type_def(vis)?)
+    } else if self.peek_keyword("agent") {
+      let keyword = self.expect("agent")?;
+      let lt_ctx = self.parse_lt_ctx(false)?;
+      let node = self.parse_node_like(false, Self::parse_agent_port)?;
+      Item::Agent(AgentDef { vis, keyword, lt_ctx, node })
+    } else if self.peek_keyword("net") {
+      let keyword = self.expect("net")?;
+      let lt_ctx = self.parse_lt_ctx(true)?;
+      let node = self.parse_node_like(false, |slf| {
+        let var = slf.parse_name()?;
+        let colon = slf.expect(":")?;
+        let label = slf.parse_port_label()?;
+        Ok(FreePort { var, colon, label })
+      })?;
+      let body = self.parse_body()?;
+      Item::Net(NetDef { vis, keyword, lt_ctx, node, body })
+    } else if vis.is_some() {
+      self.expected("type, agent, or net declaration")?
+    } else {
+      self.expected("type, agent, or rule declaration")?
+    })
+  }
+
+  fn parse_use(&mut self) -> Result<Use<'i>, String> {
+    let keyword = self.expect("use")?;
+    let module = self.parse_name()?;
+    let colons = self.expect("::")?;
+    let mut names = vec![];
+    let Some(open) = self.try_expect("{") else {
+      names.push((self.parse_use_name()?, None));
+      return Ok(Use { keyword, module, colons, braces: None, names });
+    };
+    let close = loop {
+      if let Some(close) = self.try_expect("}") {
+        break close;
+      }
+      let name = self.parse_use_name()?;
+      let comma = self.try_expect(",");
+      let done = comma.is_none();
+      names.push((name, comma));
+      if done {
+        break self.expect("}")?;
+      }
+    };
+    Ok(Use { keyword, module, colons, braces: Some((open, close)), names })
+  }
+
+  fn parse_use_name(&mut self) -> Result<UseName<'i>, String> {
+    let name = self.parse_name()?;
+    self.skip_trivia();
+    let alias = if self.peek_keyword("as") { Some((self.expect("as")?, self.parse_name()?)) } else { None };
+    Ok(UseName { name, alias })
+  }
+
+  fn parse_type_def(&mut self, vis: Option<Token<'i>>) -> Result<TypeDef<'i>, String> {
+    let keyword = self.expect("type")?;
+    let ty = self.parse_type(false)?;
+    let colon = self.expect(":")?;
+    self.skip_trivia();
+    let start = self.index;
+    match self.peek_one() {
+      Some('+' | '-') => self.advance_one(),
+      _ => self.expected("polarity")?,
+    };
+    Ok(TypeDef { vis, keyword, ty, colon, polarity: self.token(start) })
+  }
+
+  fn parse_node(&mut self) -> Result<Node<'i>, String> {
+    self.parse_node_like(true, Self::parse_arg)
+  }
+
+  fn parse_arg(&mut self) -> Result<Arg<'i>, String> {
+    let path = self.parse_path()?;
+    if let Some(colon) = self.try_expect_colon(&path) {
+      let name = self.parse_path()?;
+      return Ok(Arg { name: Some((path, colon)), port: self.parse_port(name)? });
+    }
+    Ok(Arg { name: None, port: self.parse_port(path)? })
+  }
+
+  fn parse_port(&mut self, name: Token<'i>) -> Result<Port<'i>, String> {
+    if name.text.contains("::") || self.try_peek("(") {
+      Ok(Port::Node(Box::new(self.parse_ports(name, Self::parse_arg)?)))
+    } else {
+      Ok(Port::Var(name))
+    }
+  }
+
+  fn parse_body(&mut self) -> Result<Body<'i>, String> {
+    let open = self.expect("{")?;
+    let mut nodes = vec![];
+    loop {
+      if let Some(close) = self.try_expect("}") {
+        return Ok(Body { open, nodes, close });
+      }
+      nodes.push(self.parse_node()?);
+    }
+  }
+
+  fn parse_agent_port(&mut self) -> Result<AgentPort<'i>, String> {
+    let bang = self.try_expect("!");
+    let path = self.parse_path()?;
+    if bang.is_none() {
+      if let Some(colon) = self.try_expect_colon(&path) {
+        return Ok(AgentPort { name: Some((path, colon)), label: self.parse_port_label()? });
+      }
+    }
+    let lt = self.parse_lt()?;
+    Ok(AgentPort { name: None, label: PortLabel { ty: TypeRef { bang, name: path }, lt } })
+  }
+
+  fn try_expect_colon(&mut self, name: &Token<'i>) -> Option<Token<'i>> {
+    if name.text.contains("::") {
+      return None;
+    }
+    self.try_expect(":")
+  }
+
+  fn parse_port_label(&mut self) -> Result<PortLabel<'i>, String> {
+    let ty = self.parse_type(true)?;
+    let lt = self.parse_lt()?;
+    Ok(PortLabel { ty, lt })
+  }
+
+  fn parse_type(&mut self, qualified: bool) -> Result<TypeRef<'i>, String> {
+    let bang = self.try_expect("!");
+    let name = if qualified { self.parse_path()? } else { self.parse_name()? };
+    Ok(TypeRef { bang, name })
+  }
+
+  fn parse_node_like<T>(
+    &mut self,
+    qualified: bool,
+    parse_elem: impl FnMut(&mut Self) -> Result<T, String>,
+  ) -> Result<NodeLike<'i, T>, String> {
+    let name = if qualified { self.parse_path()? } else { self.parse_name()? };
+    self.parse_ports(name, parse_elem)
+  }
+
+  fn parse_ports<T>(
+    &mut self,
+    name: Token<'i>,
+    mut parse_elem: impl FnMut(&mut Self) -> Result<T, String>,
+  ) -> Result<NodeLike<'i, T>, String> {
+    let open = self.expect("(")?;
+    let mut ports = Vec::new();
+    let close = loop {
+      if let Some(close) = self.try_expect(")") {
+        break close;
+      }
+      let elem = parse_elem(self)?;
+      let comma = self.try_expect(",");
+      let done = comma.is_none();
+      ports.push((elem, comma));
+      if done {
+        break self.expect(")")?;
+      }
+    };
+    Ok(NodeLike { name, open, ports, close })
+  }
+
+  fn parse_lt_ctx(&mut self, hole: bool) -> Result<Option<LtCtx<'i>>, String> {
+    let Some(open) = self.try_expect("[") else { return Ok(None) };
+    let mut parts = vec![];
+    if let Some(hole) = hole.then(|| self.try_expect("_")).flatten() {
+      parts.push(LtPart::Hole(hole));
+    } else if !self.try_peek("]") {
+      let mut external = true;
+      if let Some(separator) = self.try_expect("|") {
+        external = false;
+        parts.push(LtPart::Op(separator));
+      }
+      parts.push(LtPart::Decl(self.parse_lt_decl()?));
+      loop {
+        self.skip_trivia();
+        if let Some(op) = ["<=", ">=", ",", "<", ">"].into_iter().find(|op| self.peek_many(op.len()) == Some(op)) {
+          parts.push(LtPart::Op(self.expect(op)?));
+        } else {
+          match self.peek_one() {
+            Some('|') if external => {
+              external = false;
+              parts.push(LtPart::Op(self.expect("|")?));
+              if self.try_peek("]") {
+                break;
+              }
+            }
+            Some(']') => break,
+            _ => self.expected("comma, comparison operator, or separator")?,
+          }
+        }
+        parts.push(LtPart::Decl(self.parse_lt_decl()?));
+      }
+    }
+    let close = self.expect("]")?;
+    Ok(Some(LtCtx { open, parts, close }))
+  }
+
+  fn parse_lt_decl(&mut self) -> Result<LtDecl<'i>, String> {
+    let lt = self.parse_lt()?;
+    let question = self.try_expect("?");
+    Ok(LtDecl { lt, question })
+  }
+
+  fn parse_lt(&mut self) -> Result<Token<'i>, String> {
+    self.skip_trivia();
+    let start = self.index;
+    self.expect_str("'")?;
+    self.take_while(Self::is_name_char);
+    if self.index - start <= 1 {
+      self.expected("lifetime name")
+    } else {
+      Ok(self.token(start))
+    }
+  }
+
+  fn parse_name(&mut self) -> Result<Token<'i>, String> {
+    self.skip_trivia();
+    let start = self.index;
+    if self.take_while(Self::is_name_char).is_empty() {
+      self.expected("name")
+    } else {
+      Ok(self.token(start))
+    }
+  }
+
+  fn parse_path(&mut self) -> Result<Token<'i>, String> {
+    self.skip_trivia();
+    let start = self.index;
+    if self.take_while(Self::is_name_char).is_empty() {
+      return self.expected("name");
+    }
+    if self.peek_many(2) == Some("::") {
+      self.advance_many(2);
+      if self.take_while(Self::is_name_char).is_empty() {
+        return self.expected("name");
+      }
+    }
+    Ok(self.token(start))
+  }
+
+  fn token(&mut self, start: usize) -> Token<'i> {
+    Token { leading: std::mem::take(&mut self.trivia), start, text: &self.input[start..self.index] }
+  }
+
+  fn expect(&mut self, str: &str) -> Result<Token<'i>, String> {
+    self.skip_trivia();
+    let start = self.index;
+    self.expect_str(str)?;
+    Ok(self.token(start))
+  }
+
+  fn expect_str(&mut self, str: &str) -> Result<(), String> {
+    if !self.input[self.index..].starts_with(str) {
+      return Err(format!("expected `{str}`"));
+    }
+    self.index += str.len();
+    Ok(())
+  }
+
+  fn try_expect(&mut self, str: &str) -> Option<Token<'i>> {
+    self.try_peek(str).then(|| self.expect(str).unwrap())
+  }
+
+  fn peek_keyword(&mut self, keyword: &str) -> bool {
+    self.try_peek(keyword) && !self.input[self.index + keyword.len()..].starts_with(Self::is_name_char)
+  }
+
+  fn try_peek(&mut self, str: &str) -> bool {
+    self.skip_trivia();
+    self.peek_many(str.len()) == Some(str)
+  }
+
+  fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_.-/$".contains(c)
+  }
+}
@@ -2,14 +2,14 @@ use crate::{
   err,
   error::{Error, ErrorGroup},
   globals::{GlobalCtx, PortLabel},
-  lifetimes::LifetimeCtx,
+  lifetimes::{LifetimeCtx, Origin},
   new_index,
   order::Relation,
   program::Node,
   scope::Scope,
   util::Captures,
 };
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 new_index!(pub Var "variable");
 
@@ -20,68 +20,130 @@ pub struct VarCtx {
 
 #[derive(Debug, Clone, Default)]
 pub struct VarInfo {
-  pub uses: Vec<PortLabel>,
+  pub uses: Vec<(PortLabel, Site)>,
+}
+
+/// A port that a variable is connected to.
+#[derive(Debug, Clone)]
+pub enum Site {
+  /// The port with the given index, and name if it has one, of a node, e.g.
+  /// `Dup#0`.
+  Node(String, usize, Option<String>),
+  /// The free port with the given index of the net being checked.
+  Free(usize),
 }
 
 impl VarCtx {
-  pub fn infer_uses(
+  pub(crate) fn infer_uses(
     &mut self,
     errors: &mut ErrorGroup,
     globals: &GlobalCtx,
     lt_ctx: &mut LifetimeCtx,
-    nodes: &Vec<Node>,
+    nodes: &[Node],
   ) {
     for (i, node) in nodes.iter().enumerate() {
-      let lt_base = lt_ctx.import(&globals.components[node.component].lt_ctx, true, format_args!("{i}."));
+      let name = format!("{}#{i}", globals.components.name(node.component));
+      let component = &globals.components[node.component];
+      let lt_span = node.lt_span(&component.ports);
+      let lt_name = node.lt_name(&name, component, &self.vars);
+      let lt_base = lt_ctx.import(&component.lt_ctx, true, lt_name, lt_span, Origin::Contract(name.clone()));
       if let Some(pairs) = errors.push(self.check_node_arity(node, globals)) {
-        for (var, label) in pairs {
-          self.vars[var].uses.push(PortLabel(label.0, lt_base + label.1));
+        for (port, (var, label)) in pairs.enumerate() {
+          let site = Site::Node(name.clone(), port, component.port_names[port].clone());
+          self.add_use(var, PortLabel(label.0, lt_base + label.1, label.2), site);
         }
       }
     }
   }
 
-  pub fn check_types(&mut self, globals: &GlobalCtx, lt_ctx: &mut LifetimeCtx) -> ErrorGroup {
+  pub(crate) fn add_use(&mut self, var: Var, label: PortLabel, site: Site) {
+    if !self.vars.poisoned(var) {
+      self.vars[var].uses.push((label, site));
+    }
+  }
+
+  pub(crate) fn check_types(&mut self, globals: &GlobalCtx, lt_ctx: &mut LifetimeCtx) -> ErrorGroup {
     let mut errors = ErrorGroup::default();
     for (_, name, VarInfo { uses }) in self.vars.iter() {
       if uses.len() == 1 {
-        errors.push(err!("`{name}`: used only once"));
+        errors.push(err!("`{name}`: used only once").at(uses[0].0 .2));
       } else if uses.len() > 2 {
-        errors.push(err!("`{name}`: used more than twice"));
+        let mut err = err!("`{name}`: used more than twice").at(uses[2].0 .2);
+        for use_ in &uses[..2] {
+          err = err.label(use_.0 .2, "also used here");
+        }
+        errors.push(err);
       } else {
-        let &[a, b] = &uses[..] else { unreachable!() };
+        let [(a, a_site), (b, b_site)] = &uses[..] else { unreachable!() };
+        let (a, b) = (*a, *b);
         if a.0 != !b.0 {
-          errors.push(err!(
-            "`{name}`: mismatched types `{}` and `{}`",
-            globals.types.name(a.0),
-            globals.types.name(b.0),
-          ));
+          errors.push(
+            err!("`{name}`: mismatched types `{}` and `{}`", globals.types.name(a.0), globals.types.name(b.0))
+              .label(a.2, show_use(globals.types.name(a.0), a_site))
+              .label(b.2, show_use(globals.types.name(b.0), b_site)),
+          );
         } else {
-          lt_ctx.in_order.relate_polarity(a.1, b.1, Relation::LE, globals.types[a.0].polarity);
+          let origin = Origin::Wire(name.to_owned(), a_site.clone(), b_site.clone());
+          lt_ctx.in_order.relate_polarity(a.1, b.1, Relation::LE, globals.types[a.0].polarity, origin);
         }
       }
     }
     errors
   }
 
-  pub fn check_node_arity<'a>(
+  pub(crate) fn check_node_arity<'a>(
     &mut self,
     node: &'a Node,
     globals: &'a GlobalCtx,
   ) -> Result<impl Iterator<Item = (Var, PortLabel)> + Captures<&'a ()>, Error> {
-    let signature = &globals.components[node.component].ports;
+    let component = &globals.components[node.component];
+    let signature = &component.ports;
     if node.ports.len() == signature.len() {
-      Ok(node.ports.iter().copied().zip(signature.iter().copied()))
+      Ok(
+        node
+          .ports
+          .iter()
+          .zip(&node.port_spans)
+          .zip(signature)
+          .map(|((&var, &span), &label)| (var, PortLabel(label.0, label.1, span))),
+      )
     } else {
       for &var in &node.ports {
         self.vars.poison(var);
       }
-      Err(err!(
-        "`{}` expects {} ports but {} were supplied",
-        globals.components.name(node.component),
-        signature.len(),
-        node.ports.len(),
-      ))
+      // the ports are listed if any is named
+      let names = if component.port_names.iter().any(Option::is_some) {
+        let names = component.port_names.iter().map(|name| format!("`{}`", name.as_deref().unwrap_or("_")));
+        format!(" ({})", names.collect::<Vec<_>>().join(", "))
+      } else {
+        String::new()
+      };
+      Err(
+        err!(
+          "`{}` expects {} ports{names} but {} were supplied",
+          globals.components.name(node.component),
+          signature.len(),
+          node.ports.len(),
+        )
+        .at(node.span),
+      )
+    }
+  }
+}
+
+fn show_use(ty: &str, site: &Site) -> String {
+  match site {
+    Site::Node(node, _, Some(port)) => format!("`{ty}` at port `{port}` of `{node}`"),
+    _ => format!("`{ty}` here"),
+  }
+}
+
+impl Display for Site {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Site::Node(node, _, Some(name)) => write!(f, "{node} port `{name}`"),
+      Site::Node(node, port, None) => write!(f, "{node} port {port}"),
+      Site::Free(port) => write!(f, "free port {port}"),
     }
   }
 }
@@ -0,0 +1,62 @@
+use std::fmt::Write;
+
+use insta::{assert_snapshot, Settings};
+
+use inet_lifetimes::{
+  analysis::{Analysis, Location},
+  program::CheckOptions,
+  resolve::FileSystem,
+};
+
+#[test]
+fn test_analysis() {
+  let path = "tests/programs/analysis.inlt";
+  let analysis = Analysis::new(path, &CheckOptions::default(), &FileSystem);
+  let source = analysis.source(path).unwrap();
+  let show = |location: &Location| {
+    let source = analysis.source(&location.file).unwrap();
+    let line = source[..location.start].matches('\n').count() + 1;
+    format!("{}:{line} `{}`", location.file, &source[location.start..location.end])
+  };
+  let mut result = String::new();
+
+  writeln!(result, "symbols:").unwrap();
+  for symbol in analysis.symbols() {
+    writeln!(result, "  {:?} {} at {}", symbol.kind, symbol.name, show(&symbol.location)).unwrap();
+  }
+
+  writeln!(result, "diagnostics:").unwrap();
+  for diagnostic in analysis.diagnostics() {
+    let location = diagnostic.location.as_ref().map_or("nowhere".to_owned(), show);
+    writeln!(result, "  {:?} at {location}:", diagnostic.severity).unwrap();
+    for line in diagnostic.message.lines() {
+      writeln!(result, "    {line}").unwrap();
+    }
+    for (location, note) in &diagnostic.related {
+      writeln!(result, "    related: {note} at {}", show(location)).unwrap();
+    }
+  }
+
+  // each query is the text before a name, and the name
+  for (before, name) in [
+    ("agent['f | '", "f"),
+    ("Lam(", "Fn"),
+    ("Nat'i, !", "Nat"),
+    ("rule ", "Lam"),
+    ("App(f, i, o)\n  ", "add_two"),
+    ("net['a | ", "'a"),
+    ("o: !Nat", "'c"),
+    ("net['a < ", "'b"),
+  ] {
+    let offset = source.find(&format!("{before}{name}")).unwrap() + before.len();
+    writeln!(result, "`{name}` after `{}`:", before.escape_debug()).unwrap();
+    writeln!(result, "  hover: {:?}", analysis.hover(offset)).unwrap();
+    writeln!(result, "  definition: {:?}", analysis.definition(offset).as_ref().map(show)).unwrap();
+  }
+
+  let mut settings = Settings::new();
+  settings.set_prepend_module_to_snapshot(false);
+  settings.set_omit_expression(true);
+  settings.set_input_file(path);
+  settings.bind(|| assert_snapshot!(path, result));
+}
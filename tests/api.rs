@@ -0,0 +1,131 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use inet_lifetimes::{
+  check_file, check_file_with, check_source, fix, lifetimes::Side, order::Relation, program::CheckOptions,
+  resolve::SearchPaths,
+};
+
+#[test]
+fn test_lookup() {
+  let checked = check_file("examples/nat.inlt", &CheckOptions::default()).unwrap();
+  let program = &checked.program;
+  assert!(checked.warnings.is_none());
+
+  let dup = program.agent("Dup").unwrap();
+  let nat = program.globals.types.lookup("Nat").unwrap();
+  assert_eq!(dup.ports.iter().map(|port| port.0).collect::<Vec<_>>(), [!nat, nat, nat]);
+  let [a, b] = ["'a", "'b"].map(|lt| dup.lt_ctx.lifetimes.lookup(lt).unwrap());
+  assert!(dup.lt_ctx.ex_order.implies(a, b, Relation::LT));
+  assert_eq!(dup.lt_ctx.lifetimes[a].side, Side::External);
+
+  let rule = program.rule("Dup", "Succ").unwrap();
+  assert_eq!(program.globals.components.name(rule.a.component), "Succ");
+  assert!(program.net("add_two").is_some());
+  assert!(program.net("Dup").is_none());
+}
+
+#[test]
+fn test_errors() {
+  let source = "type Nat: +\nagent['a] Zero(Nat'b)\n";
+  let failure = check_source("zero.inlt", source, &CheckOptions::default(), &HashMap::new()).unwrap_err();
+  assert_eq!(failure.errors.message(), "check errors:");
+  let [agent] = failure.errors.children() else { panic!("{failure}") };
+  assert_eq!(agent.message(), "in agent `Zero`:");
+  let [undefined] = agent.children() else { panic!("{failure}") };
+  assert_eq!(undefined.message(), "undefined lifetime `'b`");
+  let span = undefined.span().unwrap();
+  assert_eq!(&failure.files[span.file].contents[span.start..span.end], "Nat'b");
+}
+
+#[test]
+fn test_memory() {
+  let files = HashMap::<PathBuf, String>::from_iter(
+    [
+      ("lib/nat.inlt", "pub type Nat: +\npub agent['a] Zero(Nat'a)\n"),
+      ("lib/a.inlt", "include b.inlt\n"),
+      ("lib/b.inlt", "include ../lib/./a.inlt\n"),
+    ]
+    .map(|(path, source)| (path.into(), source.into())),
+  );
+  let options = &CheckOptions::default();
+
+  let source = "include lib/nat.inlt\nnet['a] zero(z: Nat'a) { Zero(z) }\n";
+  let checked = check_source("main.inlt", source, options, &files).unwrap();
+  let names = checked.files.iter().map(|(_, file)| &*file.name).collect::<Vec<_>>();
+  assert_eq!(names, ["main.inlt", "lib/nat.inlt"]);
+  assert!(checked.program.net("zero").is_some());
+
+  let failure = check_file_with("lib/a.inlt", options, &files).unwrap_err();
+  let [cycle] = failure.errors.children() else { panic!("{failure}") };
+  assert_eq!(cycle.message(), "include cycle: `lib/a.inlt` -> `lib/b.inlt` -> `lib/../lib/a.inlt`");
+
+  let checked = check_source("", source, options, &files).unwrap();
+  assert!(checked.program.net("zero").is_some());
+
+  let failure = check_source("main.inlt", "include missing.inlt\n", options, &files).unwrap_err();
+  let [missing] = failure.errors.children() else { panic!("{failure}") };
+  assert_eq!(missing.message(), "cannot read `missing.inlt`: no such file in memory");
+}
+
+#[test]
+fn test_fix_in_memory() {
+  let files = HashMap::<PathBuf, String>::from_iter(
+    [("main.inlt", "type Nat: +\nagent['a | 'p] Succ(Nat'p, !Nat'a)\n")]
+      .map(|(path, source)| (path.into(), source.into())),
+  );
+  let failure = fix("main.inlt", &CheckOptions::default(), &files).unwrap_err();
+  assert_eq!(failure.to_string(), "cannot write `main.inlt`: files cannot be written");
+}
+
+#[test]
+fn test_search_paths() {
+  let files = HashMap::<PathBuf, String>::from_iter(
+    [
+      ("main.inlt", "include std/nat\ninclude two.inlt\nnet['a] main(n: Nat'a) { Two(n) }\n"),
+      ("first/two.inlt", "include std/nat\npub agent['a] Two(Nat'a)\n"),
+      ("second/two.inlt", "pub agent['a] Two(Nat'a)\n"),
+    ]
+    .map(|(path, source)| (path.into(), source.into())),
+  );
+  let options = &CheckOptions::default();
+
+  let resolver = SearchPaths { base: files.clone(), dirs: vec!["first".into(), "second".into()] };
+  let checked = check_file_with("main.inlt", options, &resolver).unwrap();
+  let names = checked.files.iter().map(|(_, file)| &*file.name).collect::<Vec<_>>();
+  assert_eq!(names, ["main.inlt", "std/nat.inlt", "first/two.inlt"]);
+  assert!(checked.program.agent("Succ").is_some());
+
+  let failure = check_file_with("main.inlt", options, &files).unwrap_err();
+  let [missing] = failure.errors.children() else { panic!("{failure}") };
+  assert_eq!(missing.message(), "cannot read `two.inlt`: no such file in memory");
+
+  let files = HashMap::<PathBuf, String>::from_iter(
+    [
+      ("main.inlt", "include std/nat\nnet['a] main(n: Nat'a) { Two(n) }\n"),
+      ("std/nat", "pub type Nat: +\npub agent['a] Two(Nat'a)\n"),
+    ]
+    .map(|(path, source)| (path.into(), source.into())),
+  );
+  let checked = check_file_with("main.inlt", options, &files).unwrap();
+  let names = checked.files.iter().map(|(_, file)| &*file.name).collect::<Vec<_>>();
+  assert_eq!(names, ["main.inlt", "std/nat"]);
+  assert!(checked.program.agent("Succ").is_none());
+}
+
+#[test]
+fn test_modules() {
+  let checked = check_file("tests/programs/modules.inlt", &CheckOptions::default()).unwrap();
+  let program = &checked.program;
+  let globals = &program.globals;
+
+  let era = program.agent("bits::Era").unwrap();
+  assert_ne!(era.id, program.agent("nat::Era").unwrap().id);
+  assert_eq!(globals.modules[globals.component_modules[era.id]].name, "bits");
+  let bit = globals.types.lookup("Bit").unwrap();
+  assert_eq!(globals.modules[globals.type_modules[bit]].name, "bits");
+
+  assert!(program.net("bits::flip").is_some());
+  assert!(program.net("nat::flip").is_none());
+  assert!(program.rule("bits::Era", "bits::One").is_some());
+  assert!(program.rule("nat::Era", "bits::One").is_none());
+}
@@ -1,10 +1,47 @@
+use std::fs;
+
 use insta::{assert_snapshot, Settings};
 
-use inet_lifetimes::check;
+use inet_lifetimes::{
+  check, check_json, fix,
+  program::{CheckOptions, Level},
+  resolve::FileSystem,
+};
 
-const OK_PATHS: &[&str] = &["examples/fn.inlt", "examples/list.inlt", "examples/nat.inlt"];
+const OK_PATHS: &[&str] = &[
+  "examples/fn.inlt",
+  "examples/list.inlt",
+  "examples/nat.inlt",
+  "tests/programs/include/bits.inlt",
+  "tests/programs/include/unmarked.inlt",
+  "tests/programs/modules.inlt",
+  "tests/programs/named_ports.inlt",
+  "tests/programs/nested.inlt",
+  "tests/programs/multiple_bounds.inlt",
+  "tests/programs/strict_bounds.inlt",
+  "std/bool.inlt",
+  "std/list.inlt",
+  "std/nat.inlt",
+];
 
-const ERR_PATHS: &[&str] = &["examples/bad/inf_nat.inlt", "tests/programs/bad.inlt"];
+const ERR_PATHS: &[&str] = &[
+  "examples/bad/inf_nat.inlt",
+  "tests/programs/bad.inlt",
+  "tests/programs/bad_include.inlt",
+  "tests/programs/bad_std.inlt",
+  "tests/programs/duplicate_rules.inlt",
+  "tests/programs/fix.inlt",
+  "tests/programs/fix_conflict.inlt",
+  "tests/programs/infer_recursive.inlt",
+  "tests/programs/module_names_bad.inlt",
+  "tests/programs/modules_bad.inlt",
+  "tests/programs/multiple_bounds_bad.inlt",
+  "tests/programs/named_ports_bad.inlt",
+  "tests/programs/nested_bad.inlt",
+  "tests/programs/strict_bounds_bad.inlt",
+  "tests/programs/syntax_errors.inlt",
+  "tests/programs/wire_names.inlt",
+];
 
 #[test]
 fn test_ok() {
@@ -12,10 +49,16 @@ fn test_ok() {
   let mut err_count = 0;
   for path in OK_PATHS {
     settings.set_input_file(path);
-    let result = check(path);
-    if let Err(err) = result {
-      println!("{path} failed: {err}");
-      err_count += 1;
+    match check(path, &CheckOptions::default(), &FileSystem) {
+      Ok(None) => {}
+      Ok(Some(warnings)) => {
+        println!("{path} has warnings: {warnings}");
+        err_count += 1;
+      }
+      Err(err) => {
+        println!("{path} failed: {err}");
+        err_count += 1;
+      }
     }
   }
   if err_count != 0 {
@@ -25,14 +68,92 @@ fn test_ok() {
 
 #[test]
 fn test_err() {
-  let mut settings = Settings::new();
   for &path in ERR_PATHS {
-    settings.set_prepend_module_to_snapshot(false);
-    settings.set_omit_expression(true);
-    settings.set_input_file(path);
-    let result = check(path).err().unwrap_or("no errors".to_owned());
-    settings.bind(|| {
-      assert_snapshot!(path, result);
-    });
+    let result =
+      check(path, &CheckOptions::default(), &FileSystem).err().map_or("no errors".to_owned(), |e| e.to_string());
+    assert_output(path, path, &result);
+  }
+}
+
+#[test]
+fn test_missing_rules() {
+  let path = "tests/programs/missing_rule.inlt";
+  let check_with = |missing_rules| check(path, &CheckOptions { missing_rules, ..Default::default() }, &FileSystem);
+  assert_eq!(check_with(Level::Allow).unwrap(), None);
+  let warnings = check_with(Level::Warn).unwrap().unwrap_or("no warnings".to_owned());
+  let errors = check_with(Level::Deny).err().map_or("no errors".to_owned(), |e| e.to_string());
+  assert_output(path, &format!("{path}@warn"), &warnings);
+  assert_output(path, &format!("{path}@deny"), &errors);
+}
+
+#[test]
+fn test_infer() {
+  let path = "tests/programs/infer.inlt";
+  let options = CheckOptions { missing_rules: Level::Deny, ..Default::default() };
+  assert_eq!(check(path, &options, &FileSystem).unwrap(), None);
+  let options = CheckOptions { inferred_contracts: Level::Warn, ..options };
+  let result = check(path, &options, &FileSystem).unwrap_or_else(|e| Some(e.to_string()));
+  let result = result.unwrap_or("no warnings".to_owned());
+  assert_output(path, path, &result);
+}
+
+#[test]
+fn test_json() {
+  for path in ["tests/programs/fix.inlt", "tests/programs/missing_rule.inlt", "tests/programs/syntax_errors.inlt"] {
+    let result = check_json(path, &CheckOptions::default(), &FileSystem)
+      .unwrap_or_else(|e| Some(e.show_json_lines().to_string()))
+      .unwrap_or("no diagnostics".to_owned());
+    assert_output(path, &format!("{path}@json"), &result);
+  }
+}
+
+#[test]
+fn test_fix() {
+  let path = "tests/programs/fix.inlt";
+  let fixed_path = format!("{}/fix.inlt", env!("CARGO_TARGET_TMPDIR"));
+  fs::copy(path, &fixed_path).unwrap();
+  assert_eq!(fix(&fixed_path, &CheckOptions::default(), &FileSystem).unwrap(), 2);
+  assert_eq!(check(&fixed_path, &CheckOptions::default(), &FileSystem).unwrap(), None);
+  let fixed = fs::read_to_string(&fixed_path).unwrap();
+  assert_output(path, &format!("{path}@fixed"), &fixed);
+
+  let failure = fix("tests/programs/syntax_errors.inlt", &CheckOptions::default(), &FileSystem).unwrap_err();
+  assert_eq!(failure.errors.message(), "syntax errors:");
+}
+
+fn assert_output(path: &str, name: &str, output: &str) {
+  let mut settings = Settings::new();
+  settings.set_prepend_module_to_snapshot(false);
+  settings.set_omit_expression(true);
+  settings.set_input_file(path);
+  settings.bind(|| {
+    assert_snapshot!(name, plain_highlights(output));
+  });
+}
+
+// replaces the coloured highlights of spans with a line of carets below the
+// highlighted source, so that snapshots show what each span covers
+fn plain_highlights(str: &str) -> String {
+  let mut output = String::new();
+  for line in str.lines() {
+    let mut carets = String::new();
+    let mut highlighted = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+      if c == '\x1b' {
+        let code = chars.by_ref().take_while(|&c| c != 'm').collect::<String>();
+        highlighted = code != "[0";
+      } else {
+        output.push(c);
+        carets.push(if highlighted { '^' } else { ' ' });
+      }
+    }
+    output.push('\n');
+    if carets.contains('^') {
+      output.push_str(carets.trim_end());
+      output.push('\n');
+    }
   }
+  output.pop();
+  output
 }
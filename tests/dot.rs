@@ -0,0 +1,49 @@
+use insta::{assert_snapshot, Settings};
+
+use inet_lifetimes::{dot, dot_orders, program::CheckOptions, resolve::FileSystem};
+
+const GRAPHS: &[(&str, &str, bool)] = &[
+  ("examples/fn.inlt", "add_two_call", false),
+  ("examples/fn.inlt", "add_two_call", true),
+  ("examples/nat.inlt", "Succ-Dup", true),
+  ("examples/nat.inlt", "Zero-Add", false),
+  ("examples/nat.inlt", "missing", false),
+];
+
+#[test]
+fn test_dot() {
+  let mut settings = Settings::new();
+  for &(path, name, lifetimes) in GRAPHS {
+    settings.set_prepend_module_to_snapshot(false);
+    settings.set_omit_expression(true);
+    settings.set_input_file(path);
+    let result = dot(path, name, lifetimes, &FileSystem).unwrap_or_else(|e| e.to_string());
+    let suffix = if lifetimes { "_lifetimes" } else { "" };
+    settings.bind(|| {
+      assert_snapshot!(format!("{path}@{name}{suffix}.dot"), result);
+    });
+  }
+}
+
+const ORDERS: &[(&str, &str)] = &[("examples/bad/inf_nat.inlt", "inf_nat"), ("examples/nat.inlt", "Succ-Dup")];
+
+#[test]
+fn test_dot_orders() {
+  let mut settings = Settings::new();
+  for &(path, name) in ORDERS {
+    settings.set_prepend_module_to_snapshot(false);
+    settings.set_omit_expression(true);
+    settings.set_input_file(path);
+    let orders = dot_orders(path, &CheckOptions::default(), &FileSystem).unwrap();
+    let (_, graph) = orders.iter().find(|(x, _)| x == name).unwrap();
+    settings.bind(|| {
+      assert_snapshot!(format!("{path}@{name}.orders.dot"), graph);
+    });
+  }
+}
+
+#[test]
+fn test_dot_orders_syntax_errors() {
+  let failure = dot_orders("tests/programs/syntax_errors.inlt", &CheckOptions::default(), &FileSystem).unwrap_err();
+  assert_eq!(failure.errors.message(), "syntax errors:");
+}
@@ -0,0 +1,41 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use insta::{assert_snapshot, Settings};
+
+use inet_lifetimes::{fmt, resolve::FileSystem};
+
+const FORMATTED_PATHS: &[&str] =
+  &["examples/fn.inlt", "examples/list.inlt", "examples/nat.inlt", "examples/bad/inf_nat.inlt"];
+
+#[test]
+fn test_formatted() {
+  for path in FORMATTED_PATHS {
+    assert!(fmt(path, true, &FileSystem).unwrap(), "{path} is not formatted");
+  }
+}
+
+#[test]
+fn test_fmt() {
+  let path = "tests/programs/fmt.inlt";
+  let formatted_path = format!("{}/fmt.inlt", env!("CARGO_TARGET_TMPDIR"));
+  fs::copy(path, &formatted_path).unwrap();
+  assert!(!fmt(&formatted_path, true, &FileSystem).unwrap());
+  assert!(!fmt(&formatted_path, false, &FileSystem).unwrap());
+  assert!(fmt(&formatted_path, true, &FileSystem).unwrap());
+  let formatted = fs::read_to_string(&formatted_path).unwrap();
+  let mut settings = Settings::new();
+  settings.set_prepend_module_to_snapshot(false);
+  settings.set_omit_expression(true);
+  settings.set_input_file(path);
+  settings.bind(|| {
+    assert_snapshot!(format!("{path}@formatted"), formatted);
+  });
+}
+
+#[test]
+fn test_fmt_in_memory() {
+  let files = HashMap::<PathBuf, String>::from_iter([("fmt.inlt".into(), "type  Nat: +\n".into())]);
+  assert!(!fmt("fmt.inlt", true, &files).unwrap());
+  let failure = fmt("fmt.inlt", false, &files).unwrap_err();
+  assert_eq!(failure.to_string(), "cannot write `fmt.inlt`: files cannot be written");
+}
@@ -0,0 +1,34 @@
+use insta::{assert_snapshot, Settings};
+
+use inet_lifetimes::{resolve::FileSystem, run};
+
+const RUNS: &[(&str, &str)] = &[
+  ("tests/programs/run.inlt", "two_times_two"),
+  ("tests/programs/run.inlt", "stuck"),
+  ("tests/programs/run.inlt", "missing"),
+  ("tests/programs/named_ports.inlt", "apply_two"),
+  ("tests/programs/nested.inlt", "two_times_two"),
+  ("examples/fn.inlt", "add_two_call"),
+];
+
+#[test]
+fn test_run() {
+  let mut settings = Settings::new();
+  for &(path, net) in RUNS {
+    settings.set_prepend_module_to_snapshot(false);
+    settings.set_omit_expression(true);
+    settings.set_input_file(path);
+    let result = run(path, net, None, &FileSystem).unwrap_or_else(|e| e.to_string());
+    settings.bind(|| {
+      assert_snapshot!(format!("{path}@{net}"), result);
+    });
+  }
+}
+
+#[test]
+fn test_run_limit() {
+  let path = "tests/programs/run.inlt";
+  assert!(run(path, "two_times_two", Some(100), &FileSystem).is_ok());
+  let err = run(path, "two_times_two", Some(3), &FileSystem).unwrap_err();
+  assert_eq!(err.to_string(), "no normal form was reached within 3 interactions");
+}
@@ -0,0 +1,64 @@
+use std::fs;
+
+use inet_lifetimes::syntax::{parse, Item, Token, Tokens, TriviaKind};
+
+const PATHS: &[&str] = &[
+  "examples/fn.inlt",
+  "examples/list.inlt",
+  "examples/nat.inlt",
+  "examples/bad/inf_nat.inlt",
+  "tests/programs/analysis.inlt",
+  "tests/programs/fix.inlt",
+  "tests/programs/fix_conflict.inlt",
+  "tests/programs/fmt.inlt",
+  "tests/programs/infer.inlt",
+  "tests/programs/modules.inlt",
+  "tests/programs/multiple_bounds.inlt",
+  "tests/programs/named_ports.inlt",
+  "tests/programs/nested.inlt",
+  "tests/programs/run.inlt",
+  "tests/programs/strict_bounds.inlt",
+];
+
+#[test]
+fn test_round_trip() {
+  for path in PATHS {
+    let source = fs::read_to_string(path).unwrap();
+    let (syntax, errors) = parse(&source);
+    assert!(errors.is_empty(), "{path}: {errors:?}");
+    assert_eq!(syntax.to_string(), source, "{path} does not round-trip");
+  }
+}
+
+#[test]
+fn test_comments() {
+  let source = "// leading\nagent['a] A(T'a) // trailing\n\nrule A(x) // inner\n B(x) {}\n// end\n";
+  let (syntax, _) = parse(source);
+  let mut comments = vec![];
+  syntax.tokens(&mut |token| {
+    let leading = token.leading.iter().filter(|x| x.kind == TriviaKind::Comment);
+    comments.extend(leading.map(|x| (x.text, token.text)));
+  });
+  assert_eq!(comments, [("// leading", "agent"), ("// trailing", "rule"), ("// inner", "B")]);
+  assert_eq!(syntax.end.iter().filter(|x| x.kind == TriviaKind::Comment).count(), 1);
+  let Item::Rule(rule) = &syntax.items[1] else { panic!("expected a rule") };
+  assert_eq!(&source[rule.b.name.start..rule.body.close.end()], "B(x) {}");
+}
+
+#[test]
+fn test_recovery() {
+  let source = fs::read_to_string("tests/programs/syntax_errors.inlt").unwrap();
+  let (syntax, errors) = parse(&source);
+  assert_eq!(errors.len(), 2);
+  assert_eq!(syntax.to_string(), source);
+  let names = syntax.items.iter().map(|item| item.keyword().text).collect::<Vec<_>>();
+  assert_eq!(names, ["type", "agent", "agent", "net"]);
+  let skipped = |token: &Token| token.leading.iter().any(|x| x.kind == TriviaKind::Skipped);
+  let mut after_skipped = vec![];
+  syntax.tokens(&mut |token| {
+    if skipped(token) {
+      after_skipped.push(token.text);
+    }
+  });
+  assert_eq!(after_skipped, ["agent", "net"]);
+}